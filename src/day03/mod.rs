@@ -1,7 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
+extern crate test;
+
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
 	let input_path = format!("src/{module_name}/input.txt");
@@ -15,102 +18,317 @@ pub fn run() {
 	let input = parse_input_02(&raw);
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
+
+	validate(&raw, 3)
+		.unwrap()
+		.iter()
+		.for_each(|d| println!("{}", d));
 }
 
-type Bag = HashSet<char>;
+// bit n is set when the item with priority n is in the bag
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+struct Bag(u64);
+
+impl Bag {
+	const ALL: Bag = Bag(u64::MAX);
+
+	fn from_items(items: &[u8]) -> Bag {
+		let bits = items
+			.iter()
+			.filter_map(|i| priority(*i))
+			.fold(0_u64, |acc, p| acc | 1 << p);
+
+		Bag(bits)
+	}
+
+	fn intersection(self, other: Bag) -> Bag {
+		Bag(self.0 & other.0)
+	}
+
+	fn len(self) -> usize {
+		self.0.count_ones() as usize
+	}
+
+	fn priorities(self) -> impl Iterator<Item = usize> {
+		let mut bits = self.0;
+		std::iter::from_fn(move || {
+			if bits == 0 {
+				return None;
+			}
+			let p = bits.trailing_zeros() as usize;
+			bits &= bits - 1;
+			Some(p)
+		})
+	}
+}
+
+fn priority(item: u8) -> Option<usize> {
+	match item {
+		b'a'..=b'z' => Some((item - b'a') as usize + 1),
+		b'A'..=b'Z' => Some((item - b'A') as usize + 27),
+		_ => None,
+	}
+}
+
+fn common_items(bags: &[Bag]) -> Bag {
+	bags.iter()
+		.fold(Bag::ALL, |common, bag| common.intersection(*bag))
+}
 
 fn parse_input_01(raw: &str) -> Vec<(Bag, Bag)> {
-	let input = raw
-		.lines()
+	raw.lines()
 		.map(|l| {
-			let mut bag_part_1 = Bag::new();
-			let mut bag_part_2 = Bag::new();
-			let length = l.len();
-			let (first_string, second_string) = l.split_at(length / 2);
-			first_string.chars().for_each(|c| {
-				bag_part_1.insert(c);
-			});
-			second_string.chars().for_each(|c| {
-				bag_part_2.insert(c);
-			});
-
-			(bag_part_1, bag_part_2)
+			let (first, second) = l.as_bytes().split_at(l.len() / 2);
+			(Bag::from_items(first), Bag::from_items(second))
 		})
-		.collect::<Vec<_>>();
-
-	input
+		.collect::<Vec<_>>()
 }
 
 fn part01(input: &Vec<(Bag, Bag)>) -> usize {
-	let priority_map = ('a'..='z')
-		.chain('A'..='Z')
-		.enumerate()
-		.map(|(a, b)| (b, a + 1))
-		.collect::<HashMap<_, _>>();
-
-	let priority_scores: usize = input
+	input
 		.iter()
-		.map(|(one, two)| {
-			let common = one.intersection(two).collect::<Vec<_>>();
-			let common = common.get(0).unwrap();
-			let score = priority_map.get(common).unwrap();
-			score
-		})
-		.sum();
-
-	priority_scores
-}
-
-fn parse_input_02(raw: &str) -> Vec<(Bag, Bag, Bag)> {
-	let line_chunks = raw.lines().array_chunks::<3>();
-	line_chunks
-		.map(|lines| {
-			let mut bag0 = Bag::new();
-			let mut bag1 = Bag::new();
-			let mut bag2 = Bag::new();
-
-			lines.get(0).unwrap().chars().for_each(|c| {
-				bag0.insert(c);
-			});
-			lines.get(1).unwrap().chars().for_each(|c| {
-				bag1.insert(c);
-			});
-			lines.get(2).unwrap().chars().for_each(|c| {
-				bag2.insert(c);
-			});
-
-			(bag0, bag1, bag2)
-		})
+		.map(|(one, two)| one.intersection(*two).priorities().next().unwrap())
+		.sum()
+}
+
+fn parse_input_02(raw: &str) -> Vec<Bag> {
+	raw.lines()
+		.map(|l| Bag::from_items(l.as_bytes()))
 		.collect::<Vec<_>>()
 }
 
-fn part02(input: &Vec<(Bag, Bag, Bag)>) -> usize {
-	let priority_map = ('a'..='z')
-		.chain('A'..='Z')
-		.enumerate()
-		.map(|(a, b)| (b, a + 1))
-		.collect::<HashMap<_, _>>();
+fn part02(input: &Vec<Bag>) -> usize {
+	group_badges(input, 3)
+		.unwrap()
+		.map(|badge| badge.priorities().next().unwrap())
+		.sum()
+}
 
-	let priority_scores: usize = input
-		.iter()
-		.map(|(one, two, three)| {
-			let first_inter = one
-				.intersection(two)
-				.map(|a| a.clone())
-				.collect::<HashSet<_>>();
-			let second_inter = first_inter.intersection(three).collect::<Vec<_>>();
-			let common = second_inter.get(0).unwrap();
-			let score = priority_map.get(common).unwrap();
-			score
-		})
-		.sum();
+// `None` for empty groups, which have no badge to share
+fn group_badges(bags: &[Bag], group_size: usize) -> Option<impl Iterator<Item = Bag> + '_> {
+	(group_size > 0).then(|| bags.chunks(group_size).map(common_items))
+}
+
+#[derive(Debug, PartialEq)]
+enum Issue {
+	OddLength(usize),
+	NoSharedItem,
+	MultipleSharedItems(Vec<char>),
+	NonLetterSharedItems(Vec<char>),
+	IncompleteGroup(usize),
+}
+
+#[derive(Debug, PartialEq)]
+struct Diagnostic {
+	lines: RangeInclusive<usize>,
+	issue: Issue,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.lines.start() == self.lines.end() {
+			write!(f, "line {}: ", self.lines.start())?;
+		} else {
+			write!(f, "lines {}-{}: ", self.lines.start(), self.lines.end())?;
+		}
+
+		match &self.issue {
+			Issue::OddLength(len) => write!(f, "odd length {}", len),
+			Issue::NoSharedItem => write!(f, "no shared item"),
+			Issue::MultipleSharedItems(cs) => {
+				write!(f, "multiple shared items {}", cs.iter().collect::<String>())
+			}
+			Issue::NonLetterSharedItems(cs) => {
+				write!(
+					f,
+					"non-letter shared items {:?}",
+					cs.iter().collect::<String>()
+				)
+			}
+			Issue::IncompleteGroup(size) => write!(f, "incomplete group of {}", size),
+		}
+	}
+}
+
+// `None` for empty groups, like `group_badges`
+fn validate(raw: &str, group_size: usize) -> Option<Vec<Diagnostic>> {
+	if group_size == 0 {
+		return None;
+	}
+
+	let lines = raw.lines().collect::<Vec<_>>();
+	let mut diagnostics = vec![];
 
-	priority_scores
+	for (i, l) in lines.iter().enumerate() {
+		let line_no = i + 1;
+		let mut push = |issue| {
+			diagnostics.push(Diagnostic {
+				lines: line_no..=line_no,
+				issue,
+			})
+		};
+
+		if l.len() % 2 != 0 {
+			push(Issue::OddLength(l.len()));
+		}
+
+		let (first, second) = l.as_bytes().split_at(l.len() / 2);
+		check_shared(&[first, second]).into_iter().for_each(push);
+	}
+
+	for (g, group) in lines.chunks(group_size).enumerate() {
+		let start = g * group_size + 1;
+		let lines = start..=(start + group.len() - 1);
+		let mut push = |issue| {
+			diagnostics.push(Diagnostic {
+				lines: lines.clone(),
+				issue,
+			})
+		};
+
+		if group.len() < group_size {
+			push(Issue::IncompleteGroup(group.len()));
+			continue;
+		}
+
+		let members = group.iter().map(|l| l.as_bytes()).collect::<Vec<_>>();
+		check_shared(&members).into_iter().for_each(push);
+	}
+
+	Some(diagnostics)
+}
+
+fn check_shared(parts: &[&[u8]]) -> Vec<Issue> {
+	let mut issues = vec![];
+
+	let bags = parts.iter().map(|p| Bag::from_items(p)).collect::<Vec<_>>();
+	let common = common_items(&bags);
+	match common.len() {
+		0 => issues.push(Issue::NoSharedItem),
+		1 => {}
+		_ => issues.push(Issue::MultipleSharedItems(
+			common.priorities().map(item).collect(),
+		)),
+	}
+
+	let mut non_letters = parts
+		.first()
+		.into_iter()
+		.flat_map(|p| p.iter())
+		.filter(|i| priority(**i).is_none())
+		.filter(|i| parts.iter().all(|p| p.contains(i)))
+		.map(|i| *i as char)
+		.collect::<Vec<_>>();
+	non_letters.sort_unstable();
+	non_letters.dedup();
+	if !non_letters.is_empty() {
+		issues.push(Issue::NonLetterSharedItems(non_letters));
+	}
+
+	issues
+}
+
+fn item(priority: usize) -> char {
+	match priority {
+		1..=26 => (b'a' + (priority - 1) as u8) as char,
+		27..=52 => (b'A' + (priority - 27) as u8) as char,
+		_ => panic!("Invalid priority {}", priority),
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::test_input::numbers;
+	use test::Bencher;
+
+	const INPUT: &str = concat!(
+		"vJrwpWtwJgWrhcsFMMfFFhFp\n",
+		"jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\n",
+		"PmmdzqPrVvPwwTWBwg\n",
+		"wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\n",
+		"ttgJtRGJQctTZtZT\n",
+		"CrZsJsPPZsGzwwsLwLmpwMDw\n",
+	);
+
+	// deterministic rucksacks with at least one shared item per line and per group of three
+	fn generate_input(lines: usize) -> String {
+		let items = ('a'..='z').chain('A'..='Z').collect::<Vec<_>>();
+		let mut next = numbers(42);
+
+		(0..lines)
+			.map(|l| {
+				let badge = items[(l / 3) % items.len()];
+				let shared = items[next() % items.len()];
+				let half = 10 + next() % 10;
+				let mut first = vec![shared, badge];
+				let mut second = vec![shared];
+				first.extend((2..half).map(|_| items[next() % items.len()]));
+				second.extend((1..half).map(|_| items[next() % items.len()]));
+				first.into_iter().chain(second).collect::<String>() + "\n"
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_bag() {
+		let bag = Bag::from_items(b"aAzZa");
+		assert_eq!(bag.len(), 4);
+		assert_eq!(bag.priorities().collect::<Vec<_>>(), vec![1, 26, 27, 52]);
+
+		let other = Bag::from_items(b"zZb");
+		assert_eq!(
+			bag.intersection(other).priorities().collect::<Vec<_>>(),
+			vec![26, 52]
+		);
+
+		let group = [bag, other, Bag::from_items(b"Zq")];
+		assert_eq!(common_items(&group), Bag::from_items(b"Z"));
+		assert_eq!(common_items(&[]), Bag::ALL);
+	}
+
+	#[test]
+	fn test_group_badges() {
+		let input = parse_input_02(INPUT);
+
+		let pairs = group_badges(&input, 2)
+			.unwrap()
+			.map(|b| b.len())
+			.collect::<Vec<_>>();
+		assert_eq!(pairs, vec![5, 5, 3]);
+
+		let all = group_badges(&input, 6).unwrap().collect::<Vec<_>>();
+		assert_eq!(all, vec![Bag(0)]);
+
+		assert!(group_badges(&input, 0).is_none());
+	}
+
+	#[test]
+	fn test_validate() {
+		assert_eq!(validate(INPUT, 3), Some(vec![]));
+		assert_eq!(validate(INPUT, 0), None);
+
+		let raw = concat!("abcABC\n", "abcabc\n", "ab-cd-\n", "abcxabc\n", "zzzz\n",);
+		let diagnostics = validate(raw, 3).unwrap();
+		let rendered = diagnostics
+			.iter()
+			.map(|d| d.to_string())
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			rendered,
+			vec![
+				"line 1: no shared item",
+				"line 2: multiple shared items abc",
+				"line 3: no shared item",
+				"line 3: non-letter shared items \"-\"",
+				"line 4: odd length 7",
+				"line 4: multiple shared items abc",
+				"lines 1-3: multiple shared items abc",
+				"lines 4-5: incomplete group of 2",
+			]
+		);
+	}
 
 	#[test]
 	fn test_part01() {
@@ -143,4 +361,16 @@ mod tests {
 		let result = part02(&input);
 		assert_eq!(result, 70);
 	}
+
+	#[bench]
+	fn bench_part01(b: &mut Bencher) {
+		let raw = generate_input(300_000);
+		b.iter(|| part01(&parse_input_01(&raw)));
+	}
+
+	#[bench]
+	fn bench_part02(b: &mut Bencher) {
+		let raw = generate_input(300_000);
+		b.iter(|| part02(&parse_input_02(&raw)));
+	}
 }
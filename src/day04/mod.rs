@@ -17,72 +17,219 @@ pub fn run() {
 	let part02_result = part02(&input);
 
 	println!("part02 {:?}", part02_result);
+
+	let elves = elves(&input);
+	let uncovered = uncovered_sections(&elves);
+	println!("uncovered {:?}", uncovered);
+
+	let crowded = sections_assigned_to_more_than(&elves, 2);
+	println!("assigned to more than 2 {:?}", crowded);
+
+	let redundant = redundant_work(&elves);
+	println!("redundant {:?}", redundant);
+
+	let overlaps = overlap_graph(&elves);
+	println!("overlapping elf pairs {:?}", overlaps.len());
+
+	let disjoint = input
+		.iter()
+		.map(summarize)
+		.filter(|s| s.intersection.is_none())
+		.count();
+	println!("groups without common sections {:?}", disjoint);
 }
 
-fn parse_input(raw: &str) -> Vec<(RangeInclusive<usize>, RangeInclusive<usize>)> {
+type Group = Vec<RangeInclusive<usize>>;
+
+fn parse_input(raw: &str) -> Vec<Group> {
 	let input = raw
 		.lines()
 		.map(|l| {
-			let mut split = l.split(',');
-			let first = split.next().unwrap();
-			let second = split.next().unwrap();
-
-			let mut first_split = first.split('-');
-			let first_range_start = first_split
-				.next()
-				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
-			let first_range_end = first_split
-				.next()
-				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
-
-			let mut second_split = second.split('-');
-			let second_range_start = second_split
-				.next()
-				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
-			let second_range_end = second_split
-				.next()
-				.and_then(|s| s.parse::<usize>().ok())
-				.unwrap();
-
-			let first_range = first_range_start..=first_range_end;
-			let second_range = second_range_start..=second_range_end;
-
-			(first_range, second_range)
+			l.split(',')
+				.map(|r| {
+					let mut range_split = r.split('-');
+					let range_start = range_split
+						.next()
+						.and_then(|s| s.parse::<usize>().ok())
+						.unwrap();
+					let range_end = range_split
+						.next()
+						.and_then(|s| s.parse::<usize>().ok())
+						.unwrap();
+
+					range_start..=range_end
+				})
+				.collect::<Group>()
 		})
 		.collect::<Vec<_>>();
 
 	input
 }
 
-fn part01(input: &Vec<(RangeInclusive<usize>, RangeInclusive<usize>)>) -> usize {
-	let contained_pairs = input
+fn part01(input: &Vec<Group>) -> usize {
+	let contained_groups = input
 		.iter()
-		.filter(|p| {
-			(p.0.contains(&p.1.start()) && p.0.contains(&p.1.end()))
-				|| (p.1.contains(&p.0.start()) && p.1.contains(&p.0.end()))
-		})
+		.filter(|g| !subsumed_members(g).is_empty())
 		.collect::<Vec<_>>()
 		.len();
 
-	contained_pairs
+	contained_groups
 }
 
-fn part02(input: &Vec<(RangeInclusive<usize>, RangeInclusive<usize>)>) -> usize {
-	let contained_pairs = input
+fn part02(input: &Vec<Group>) -> usize {
+	let overlapping_groups = input
 		.iter()
-		.filter(|p| {
-			p.0.contains(p.1.start())
-				|| p.0.contains(p.1.end())
-				|| p.1.contains(p.0.start())
-				|| p.1.contains(p.0.end())
+		.filter(|g| {
+			g.iter()
+				.enumerate()
+				.any(|(i, a)| g[(i + 1)..].iter().any(|b| overlaps(a, b)))
 		})
 		.collect::<Vec<_>>()
 		.len();
 
-	contained_pairs
+	overlapping_groups
+}
+
+fn contains(outer: &RangeInclusive<usize>, inner: &RangeInclusive<usize>) -> bool {
+	outer.contains(inner.start()) && outer.contains(inner.end())
+}
+
+fn overlaps(a: &RangeInclusive<usize>, b: &RangeInclusive<usize>) -> bool {
+	a.start() <= b.end() && b.start() <= a.end()
+}
+
+#[derive(Debug, PartialEq)]
+struct GroupSummary {
+	intersection: Option<RangeInclusive<usize>>,
+	span: RangeInclusive<usize>,
+	subsumed: Vec<usize>,
+}
+
+fn summarize(group: &Group) -> GroupSummary {
+	let max_start = *group.iter().map(|r| r.start()).max().unwrap();
+	let min_end = *group.iter().map(|r| r.end()).min().unwrap();
+	let intersection = if max_start <= min_end {
+		Some(max_start..=min_end)
+	} else {
+		None
+	};
+
+	let min_start = *group.iter().map(|r| r.start()).min().unwrap();
+	let max_end = *group.iter().map(|r| r.end()).max().unwrap();
+
+	GroupSummary {
+		intersection,
+		span: min_start..=max_end,
+		subsumed: subsumed_members(group),
+	}
+}
+
+// members whose sections are all covered by another single member
+fn subsumed_members(group: &Group) -> Vec<usize> {
+	group
+		.iter()
+		.enumerate()
+		.filter(|(i, inner)| {
+			group
+				.iter()
+				.enumerate()
+				.any(|(j, outer)| *i != j && contains(outer, inner))
+		})
+		.map(|(i, _)| i)
+		.collect::<Vec<_>>()
+}
+
+fn elves(input: &[Group]) -> Vec<RangeInclusive<usize>> {
+	input.iter().flatten().cloned().collect::<Vec<_>>()
+}
+
+// number of elves assigned to each section, indexed by section id
+fn coverage(elves: &[RangeInclusive<usize>]) -> Vec<usize> {
+	let max_section = elves.iter().map(|r| *r.end()).max().unwrap_or(0);
+	let mut deltas = vec![0_isize; max_section + 2];
+
+	for r in elves.iter().filter(|r| !r.is_empty()) {
+		deltas[*r.start()] += 1;
+		deltas[*r.end() + 1] -= 1;
+	}
+
+	let mut count = 0;
+	deltas
+		.iter()
+		.take(max_section + 1)
+		.map(|d| {
+			count += d;
+			count as usize
+		})
+		.collect::<Vec<_>>()
+}
+
+fn sections_where<F>(elves: &[RangeInclusive<usize>], predicate: F) -> Vec<RangeInclusive<usize>>
+where
+	F: Fn(usize) -> bool,
+{
+	let Some(min_section) = elves.iter().map(|r| *r.start()).min() else {
+		return vec![];
+	};
+
+	let mut sections = vec![];
+	let mut run_start = None;
+
+	for (section, count) in coverage(elves).into_iter().enumerate().skip(min_section) {
+		match (predicate(count), run_start) {
+			(true, None) => run_start = Some(section),
+			(false, Some(start)) => {
+				sections.push(start..=(section - 1));
+				run_start = None;
+			}
+			_ => {}
+		}
+	}
+
+	if let Some(start) = run_start {
+		let max_section = elves.iter().map(|r| *r.end()).max().unwrap();
+		sections.push(start..=max_section);
+	}
+
+	sections
+}
+
+fn uncovered_sections(elves: &[RangeInclusive<usize>]) -> Vec<RangeInclusive<usize>> {
+	sections_where(elves, |count| count == 0)
+}
+
+fn sections_assigned_to_more_than(
+	elves: &[RangeInclusive<usize>],
+	k: usize,
+) -> Vec<RangeInclusive<usize>> {
+	sections_where(elves, |count| count > k)
+}
+
+// section-assignments beyond the first elf covering each section
+fn redundant_work(elves: &[RangeInclusive<usize>]) -> usize {
+	coverage(elves)
+		.into_iter()
+		.map(|count| count.saturating_sub(1))
+		.sum()
+}
+
+// pairs of elf indices whose assignments share at least one section
+fn overlap_graph(elves: &[RangeInclusive<usize>]) -> Vec<(usize, usize)> {
+	let mut order = (0..elves.len()).collect::<Vec<_>>();
+	order.sort_by_key(|i| elves[*i].start());
+
+	let mut edges = vec![];
+	for (pos, i) in order.iter().enumerate() {
+		for j in order[(pos + 1)..].iter() {
+			if elves[*j].start() > elves[*i].end() {
+				break;
+			}
+			edges.push((*i.min(j), *i.max(j)));
+		}
+	}
+
+	edges.sort_unstable();
+	edges
 }
 
 #[cfg(test)]
@@ -106,6 +253,71 @@ mod tests {
 		assert_eq!(result, 2);
 	}
 
+	#[test]
+	fn test_groups() {
+		let raw = concat!("2-8,3-7,4-9\n", "1-3,5-6\n", "5-5\n", "1-4,2-3,1-4,2-2\n",);
+
+		let input = parse_input(raw);
+		assert_eq!(input[0], vec![2..=8, 3..=7, 4..=9]);
+
+		let summaries = input.iter().map(summarize).collect::<Vec<_>>();
+		assert_eq!(
+			summaries,
+			vec![
+				GroupSummary {
+					intersection: Some(4..=7),
+					span: 2..=9,
+					subsumed: vec![1],
+				},
+				GroupSummary {
+					intersection: None,
+					span: 1..=6,
+					subsumed: vec![],
+				},
+				GroupSummary {
+					intersection: Some(5..=5),
+					span: 5..=5,
+					subsumed: vec![],
+				},
+				GroupSummary {
+					intersection: Some(2..=2),
+					span: 1..=4,
+					subsumed: vec![0, 1, 2, 3],
+				},
+			]
+		);
+
+		assert_eq!(part01(&input), 2);
+		assert_eq!(part02(&input), 2);
+	}
+
+	#[test]
+	fn test_coverage_queries() {
+		let raw = concat!(
+			"2-4,6-8\n",
+			"2-3,4-5\n",
+			"5-7,7-9\n",
+			"2-8,3-7\n",
+			"6-6,4-6\n",
+			"2-6,4-8\n",
+			"12-13,15-15\n",
+		);
+
+		let input = parse_input(raw);
+		let elves = elves(&input);
+
+		assert_eq!(uncovered_sections(&elves), vec![10..=11, 14..=14]);
+		assert_eq!(sections_assigned_to_more_than(&elves, 6), vec![4..=6]);
+		assert_eq!(sections_assigned_to_more_than(&elves, 20), vec![]);
+		assert_eq!(redundant_work(&elves), 34);
+
+		let overlaps = overlap_graph(&elves);
+		assert_eq!(overlaps.len(), 49);
+		assert!(overlaps.contains(&(0, 11)));
+		assert!(!overlaps.contains(&(0, 1)));
+		assert!(!overlaps.contains(&(12, 13)));
+	}
+
 	#[test]
 	fn test_part02() {
 		let raw = concat!(
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::num::NonZeroUsize;
 use std::path::Path;
 
 pub fn run() {
@@ -8,27 +10,86 @@ pub fn run() {
 	let path = Path::new(&input_path);
 	let raw = fs::read_to_string(path).expect("Could not read input.txt");
 	let input = parse_input(&raw);
+	print!("{}", render_stacks(&input.0));
 
 	let part01_result = part01(&input);
 	println!("part01 {:?}", part01_result);
 
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
+
+	let mut replay = Replay::new(&input.0, &input.1, CrateMover9000);
+	let first_top = part01_result.chars().next().unwrap();
+	let topped_at = replay.first_where(on_top(first_top));
+	println!("{} on top after step {:?}", first_top, topped_at);
+
+	let limited = simulate(&input.0, &input.1, &mut LimitedCrane::new(3).unwrap());
+	println!("limited crane {:?}", top_crates(&limited));
+
+	let flipping = simulate(&input.0, &input.1, &mut FlippingCrane::default());
+	println!("flipping crane {:?}", top_crates(&flipping));
+
+	match validate(&input.0, &input.1, 2) {
+		Ok(()) => {
+			let mut multi = MultiCrane::new(CrateMover9001, 2).unwrap();
+			let multi = simulate(&input.0, &input.1, &mut multi);
+			println!("multi crane {:?}", top_crates(&multi));
+		}
+		Err(e) => println!("multi crane {:?}", e),
+	}
+
+	let shortcut = plan(&input.0, &part02_result, &CrateMover9001);
+	println!(
+		"shortcut to {} {:?}",
+		part02_result,
+		shortcut.map(|s| s.len())
+	);
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 struct Step {
 	source: usize,
 	target: usize,
 	amount: usize,
 }
 
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"move {} from {} to {}",
+			self.amount, self.source, self.target
+		)
+	}
+}
+
 type Stacks = HashMap<usize, Vec<char>>;
 type Steps = Vec<Step>;
 
 fn parse_input(raw: &str) -> (Stacks, Steps) {
 	let (raw_stacks, raw_steps) = raw.split_once("\n\n").unwrap();
 
+	let stacks = parse_stacks(raw_stacks);
+
+	let steps = raw_steps
+		.lines()
+		.map(|l| {
+			let mut step_split = l.split_whitespace();
+			let amount = step_split.nth(1).unwrap().parse::<usize>().unwrap();
+			let source = step_split.nth(1).unwrap().parse::<usize>().unwrap();
+			let target = step_split.nth(1).unwrap().parse::<usize>().unwrap();
+			Step {
+				amount,
+				source,
+				target,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	(stacks, steps)
+}
+
+fn parse_stacks(raw_stacks: &str) -> Stacks {
 	let mut raw_stacks_lines = raw_stacks.lines().rev();
 	let raw_stacks_length = raw_stacks_lines.next().unwrap();
 	let stacks_length = raw_stacks_length
@@ -36,7 +97,7 @@ fn parse_input(raw: &str) -> (Stacks, Steps) {
 		.collect::<Vec<_>>()
 		.len();
 
-	let mut stacks = HashMap::new();
+	let mut stacks = (1..=stacks_length).map(|i| (i, vec![])).collect::<Stacks>();
 
 	raw_stacks_lines.for_each(|l| {
 		let mut chunks = l.chars().array_chunks::<4>();
@@ -71,57 +132,568 @@ fn parse_input(raw: &str) -> (Stacks, Steps) {
 		}
 	});
 
-	let steps = raw_steps
-		.lines()
-		.map(|l| {
-			let mut step_split = l.split_whitespace();
-			let amount = step_split.nth(1).unwrap().parse::<usize>().unwrap();
-			let source = step_split.nth(1).unwrap().parse::<usize>().unwrap();
-			let target = step_split.nth(1).unwrap().parse::<usize>().unwrap();
-			Step {
-				amount,
-				source,
-				target,
-			}
+	stacks
+}
+
+fn render_stacks(stacks: &Stacks) -> String {
+	let stacks_length = stacks.keys().max().copied().unwrap_or(0);
+	let height = stacks.values().map(|s| s.len()).max().unwrap_or(0);
+
+	let mut lines = (0..height)
+		.rev()
+		.map(|level| {
+			(1..=stacks_length)
+				.map(|i| match stacks.get(&i).and_then(|s| s.get(level)) {
+					Some(c) => format!("[{}]", c),
+					None => String::from("   "),
+				})
+				.collect::<Vec<_>>()
+				.join(" ")
 		})
 		.collect::<Vec<_>>();
 
-	(stacks, steps)
+	let footer = (1..=stacks_length)
+		.map(|i| format!("{:^3}", i))
+		.collect::<Vec<_>>()
+		.join(" ");
+	lines.push(footer);
+
+	lines.join("\n") + "\n"
 }
 
 fn part01(input: &(Stacks, Steps)) -> String {
-	let mut stacks = input.0.clone();
-	let steps = &input.1;
+	let stacks = simulate(&input.0, &input.1, &mut CrateMover9000);
 
-	steps.iter().for_each(|step| {
-		for _ in 0..step.amount {
-			let [source, target] = stacks.get_many_mut([&step.source, &step.target]).unwrap();
-			let char = source.pop().unwrap();
-			target.push(char);
-		}
-	});
+	top_crates(&stacks)
+}
+
+fn part02(input: &(Stacks, Steps)) -> String {
+	let stacks = simulate(&input.0, &input.1, &mut CrateMover9001);
+
+	top_crates(&stacks)
+}
+
+fn simulate<C: Crane>(stacks: &Stacks, steps: &Steps, crane: &mut C) -> Stacks {
+	let mut stacks = stacks.clone();
 
-	let top_stacks = (1..(stacks.len() + 1)).map(|i| stacks.get(&i).unwrap().last().unwrap());
+	steps
+		.chunks(crane.steps_at_once())
+		.for_each(|batch| crane.operate(&mut stacks, batch));
+
+	stacks
+}
+
+fn top_crates(stacks: &Stacks) -> String {
+	let top_stacks = (1..(stacks.len() + 1)).filter_map(|i| stacks.get(&i)?.last());
 
 	top_stacks.collect::<String>()
 }
 
-fn part02(input: &(Stacks, Steps)) -> String {
-	let mut stacks = input.0.clone();
-	let steps = &input.1;
+trait Crane {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step);
+
+	// number of consecutive steps carried out at the same time
+	fn steps_at_once(&self) -> usize {
+		1
+	}
+
+	fn operate(&mut self, stacks: &mut Stacks, steps: &[Step]) {
+		steps.iter().for_each(|step| self.move_crates(stacks, step));
+	}
+}
+
+// takes the top `amount` crates off a stack, bottom-most first
+fn lift(stacks: &mut Stacks, source: usize, amount: usize) -> Vec<char> {
+	let source = stacks.get_mut(&source).unwrap();
+	source.split_off(source.len() - amount)
+}
 
-	steps.iter().for_each(|step| {
-		let [source, target] = stacks.get_many_mut([&step.source, &step.target]).unwrap();
-		let mut chars = (0..step.amount)
-			.map(|_| source.pop().unwrap())
+fn land(stacks: &mut Stacks, target: usize, mut crates: Vec<char>) {
+	stacks.entry(target).or_default().append(&mut crates);
+}
+
+#[derive(Clone)]
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step) {
+		let mut crates = lift(stacks, step.source, step.amount);
+		crates.reverse();
+		land(stacks, step.target, crates);
+	}
+}
+
+#[derive(Clone)]
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step) {
+		let crates = lift(stacks, step.source, step.amount);
+		land(stacks, step.target, crates);
+	}
+}
+
+// moves at most `capacity` crates per lift, keeping their order
+#[derive(Clone)]
+struct LimitedCrane {
+	capacity: NonZeroUsize,
+}
+
+impl LimitedCrane {
+	// a crane that can't lift anything would never finish a step
+	fn new(capacity: usize) -> Option<Self> {
+		NonZeroUsize::new(capacity).map(|capacity| LimitedCrane { capacity })
+	}
+}
+
+impl Crane for LimitedCrane {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step) {
+		let mut left = step.amount;
+		while left > 0 {
+			let amount = left.min(self.capacity.get());
+			let crates = lift(stacks, step.source, amount);
+			land(stacks, step.target, crates);
+			left -= amount;
+		}
+	}
+}
+
+// keeps the order of every other batch and flips the ones in between
+#[derive(Default, Clone)]
+struct FlippingCrane {
+	flip_next: bool,
+}
+
+impl Crane for FlippingCrane {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step) {
+		let mut crates = lift(stacks, step.source, step.amount);
+		if self.flip_next {
+			crates.reverse();
+		}
+		self.flip_next = !self.flip_next;
+		land(stacks, step.target, crates);
+	}
+}
+
+// `count` cranes of the same model each take one of the next steps,
+// lifting all their crates before any of them are put down
+#[derive(Clone)]
+struct MultiCrane<C: Crane> {
+	crane: C,
+	count: NonZeroUsize,
+}
+
+impl<C: Crane> MultiCrane<C> {
+	fn new(crane: C, count: usize) -> Option<Self> {
+		NonZeroUsize::new(count).map(|count| MultiCrane { crane, count })
+	}
+}
+
+impl<C: Crane> Crane for MultiCrane<C> {
+	fn move_crates(&mut self, stacks: &mut Stacks, step: &Step) {
+		self.crane.move_crates(stacks, step);
+	}
+
+	fn steps_at_once(&self) -> usize {
+		self.count.get()
+	}
+
+	fn operate(&mut self, stacks: &mut Stacks, steps: &[Step]) {
+		let lifted = steps
+			.iter()
+			.map(|step| lift(stacks, step.source, step.amount))
 			.collect::<Vec<_>>();
-		chars.reverse();
-		target.append(&mut chars);
+
+		steps.iter().zip(lifted).for_each(|(step, crates)| {
+			let mut hook = HashMap::from([(0, crates), (1, vec![])]);
+			let hook_step = Step {
+				source: 0,
+				target: 1,
+				amount: step.amount,
+			};
+			self.crane.move_crates(&mut hook, &hook_step);
+			land(stacks, step.target, hook.remove(&1).unwrap());
+		});
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum StepError {
+	UnknownStack(usize),
+	NotEnoughCrates { available: usize, requested: usize },
+}
+
+#[derive(Debug, PartialEq)]
+struct InvalidStep {
+	index: usize,
+	error: StepError,
+}
+
+// checks stack heights only, with `steps_at_once` steps lifting before any crates land
+fn validate(stacks: &Stacks, steps: &[Step], steps_at_once: usize) -> Result<(), InvalidStep> {
+	let mut heights = stacks
+		.iter()
+		.map(|(i, s)| (*i, s.len()))
+		.collect::<HashMap<_, _>>();
+
+	for (b, batch) in steps.chunks(steps_at_once).enumerate() {
+		for (s, step) in batch.iter().enumerate() {
+			let index = b * steps_at_once + s;
+			let invalid = |error| InvalidStep { index, error };
+
+			if !heights.contains_key(&step.target) {
+				return Err(invalid(StepError::UnknownStack(step.target)));
+			}
+			let available = heights
+				.get_mut(&step.source)
+				.ok_or_else(|| invalid(StepError::UnknownStack(step.source)))?;
+			if *available < step.amount {
+				return Err(invalid(StepError::NotEnoughCrates {
+					available: *available,
+					requested: step.amount,
+				}));
+			}
+			*available -= step.amount;
+		}
+
+		for step in batch {
+			*heights.get_mut(&step.target).unwrap() += step.amount;
+		}
+	}
+
+	Ok(())
+}
+
+// searches for a sequence that brings the wanted crate to the top of each stack
+// in turn, parking whatever is above it on stacks that are not finished yet and
+// shuffling single crates around when that alone does not get there; when the
+// search runs out, the crates are dug out one by one instead
+fn plan<C: Crane + Clone>(stacks: &Stacks, top_row: &str, crane: &C) -> Option<Steps> {
+	let wanted = top_row.chars().collect::<Vec<_>>();
+	if wanted.len() != stacks.len() || !(1..=wanted.len()).all(|i| stacks.contains_key(&i)) {
+		return None;
+	}
+
+	// with fewer than three stacks there is nowhere to dig to, but little to search
+	let can_dig = wanted.len() >= 3;
+	let budget = if can_dig { 2_000 } else { usize::MAX };
+
+	let mut planner = Planner {
+		wanted,
+		max_steps: 0,
+		budget,
+	};
+	if !planner.still_possible(stacks, &[]) {
+		return None;
+	}
+
+	// deepen the allowed number of steps so shorter plans are found first
+	let found = (0..=(3 * planner.wanted.len())).find_map(|max_steps| {
+		planner.max_steps = max_steps;
+		planner.budget = budget;
+		let mut steps = vec![];
+		planner
+			.search(stacks, &mut vec![], &mut steps, crane)
+			.then_some(steps)
 	});
 
-	let top_stacks = (1..(stacks.len() + 1)).map(|i| stacks.get(&i).unwrap().last().unwrap());
+	found.or_else(|| can_dig.then(|| dig(stacks, &planner.wanted)))
+}
 
-	top_stacks.collect::<String>()
+// a long plan of single crate moves, which every crane carries out the same way,
+// for at least three stacks holding every wanted crate: each stack in turn is
+// emptied onto the others and gets its crate dug out of them, and the last two,
+// which have nowhere else to park, borrow the first and put it back as it was
+fn dig(stacks: &Stacks, wanted: &[char]) -> Steps {
+	let mut stacks = stacks.clone();
+	let mut steps = vec![];
+	let mut shift = |stacks: &mut Stacks, source: usize, target: usize| {
+		let crates = lift(stacks, source, 1);
+		land(stacks, target, crates);
+		steps.push(Step {
+			amount: 1,
+			source,
+			target,
+		});
+	};
+	let n = wanted.len();
+
+	for target in 1..=(n - 2) {
+		let c = wanted[target - 1];
+		while !stacks[&target].is_empty() {
+			shift(&mut stacks, target, target + 1);
+		}
+
+		let above = |stacks: &Stacks, i: usize| stacks[&i].iter().rev().position(|x| *x == c);
+		let source = ((target + 1)..=n)
+			.filter_map(|i| Some((above(&stacks, i)?, i)))
+			.min()
+			.unwrap()
+			.1;
+		let parking = ((target + 1)..=n).find(|i| *i != source).unwrap();
+		while stacks[&source].last() != Some(&c) {
+			shift(&mut stacks, source, parking);
+		}
+		shift(&mut stacks, source, target);
+	}
+
+	let (c, d) = (wanted[n - 2], wanted[n - 1]);
+	let (target, last, borrowed) = (n - 1, n, 1);
+	while !stacks[&last].is_empty() {
+		shift(&mut stacks, last, target);
+	}
+
+	let height = stacks[&borrowed].len();
+	while stacks[&target].last() != Some(&d) {
+		shift(&mut stacks, target, borrowed);
+	}
+	shift(&mut stacks, target, last);
+	while stacks[&borrowed].len() > height {
+		shift(&mut stacks, borrowed, target);
+	}
+
+	if stacks[&target].last() != Some(&c) {
+		while stacks[&target].last() != Some(&c) {
+			shift(&mut stacks, target, last);
+		}
+		shift(&mut stacks, target, borrowed);
+		while stacks[&last].len() > 1 {
+			shift(&mut stacks, last, target);
+		}
+		shift(&mut stacks, borrowed, target);
+	}
+
+	steps
+}
+
+struct Planner {
+	wanted: Vec<char>,
+	max_steps: usize,
+	budget: usize,
+}
+
+impl Planner {
+	fn search<C: Crane + Clone>(
+		&mut self,
+		stacks: &Stacks,
+		finished: &mut Vec<usize>,
+		steps: &mut Steps,
+		crane: &C,
+	) -> bool {
+		if finished.len() == self.wanted.len() {
+			return true;
+		}
+		if self.budget == 0 || !self.still_possible(stacks, finished) {
+			return false;
+		}
+		self.budget -= 1;
+
+		let open = (1..=self.wanted.len())
+			.filter(|i| !finished.contains(i))
+			.collect::<Vec<_>>();
+
+		// steps to try, with the stack they finish if any; finishing a stack
+		// is tried before shuffling single crates between open stacks
+		let mut candidates: Vec<(Option<usize>, Steps)> = vec![];
+		for target in open.iter() {
+			for source in open.iter() {
+				let wanted_crate = self.wanted[target - 1];
+				let Some(above) = stacks[source].iter().rev().position(|c| *c == wanted_crate)
+				else {
+					continue;
+				};
+
+				let take = Step {
+					amount: 1,
+					source: *source,
+					target: *target,
+				};
+				if above == 0 {
+					let take = (source != target).then_some(take);
+					candidates.push((Some(*target), take.into_iter().collect()));
+					continue;
+				}
+				for parking in open.iter().filter(|p| *p != source) {
+					let park = Step {
+						amount: above,
+						source: *source,
+						target: *parking,
+					};
+					let take = (source != target).then_some(take.clone());
+					candidates.push((Some(*target), [park].into_iter().chain(take).collect()));
+				}
+			}
+		}
+		candidates.sort_by_key(|(target, new_steps)| {
+			let above = new_steps.first().map(|s| s.amount).unwrap_or(0);
+			let parks_on_target = new_steps.first().map(|s| Some(s.target)) == Some(*target);
+			(new_steps.len(), above, !parks_on_target)
+		});
+
+		for source in open.iter().filter(|i| !stacks[i].is_empty()) {
+			for target in open.iter().filter(|i| *i != source) {
+				let shuffle = Step {
+					amount: 1,
+					source: *source,
+					target: *target,
+				};
+				candidates.push((None, vec![shuffle]));
+			}
+		}
+
+		for (target, mut new_steps) in candidates {
+			if steps.len() + new_steps.len() > self.max_steps {
+				continue;
+			}
+
+			let mut crane = crane.clone();
+			let mut stacks = stacks.clone();
+			new_steps
+				.iter()
+				.for_each(|step| crane.move_crates(&mut stacks, step));
+
+			let depth = steps.len();
+			steps.append(&mut new_steps);
+			finished.extend(target);
+
+			if self.search(&stacks, finished, steps, &crane) {
+				return true;
+			}
+
+			if target.is_some() {
+				finished.pop();
+			}
+			steps.truncate(depth);
+		}
+
+		false
+	}
+
+	// every crate still wanted must be somewhere in a stack that is not finished
+	fn still_possible(&self, stacks: &Stacks, finished: &[usize]) -> bool {
+		let mut counts: HashMap<char, isize> = HashMap::new();
+		for i in (1..=self.wanted.len()).filter(|i| !finished.contains(i)) {
+			stacks[&i]
+				.iter()
+				.for_each(|c| *counts.entry(*c).or_default() += 1);
+			*counts.entry(self.wanted[i - 1]).or_default() -= 1;
+		}
+
+		counts.values().all(|c| *c >= 0)
+	}
+}
+
+// what is needed to undo one batch of steps: the crane as it was before,
+// and for each touched stack the length of its untouched bottom part and
+// the crates that were above it
+struct Reversal<C> {
+	crane: C,
+	steps: usize,
+	restore: Vec<(usize, usize, Vec<char>)>,
+}
+
+struct Replay<'a, C: Crane + Clone> {
+	crane: C,
+	steps: &'a [Step],
+	stacks: Stacks,
+	position: usize,
+	log: Vec<Reversal<C>>,
+}
+
+impl<'a, C: Crane + Clone> Replay<'a, C> {
+	fn new(stacks: &Stacks, steps: &'a [Step], crane: C) -> Self {
+		Replay {
+			crane,
+			steps,
+			stacks: stacks.clone(),
+			position: 0,
+			log: vec![],
+		}
+	}
+
+	fn stacks(&self) -> &Stacks {
+		&self.stacks
+	}
+
+	// number of steps applied so far
+	fn position(&self) -> usize {
+		self.position
+	}
+
+	fn forward(&mut self) -> bool {
+		if self.position >= self.steps.len() {
+			return false;
+		}
+
+		let end = (self.position + self.crane.steps_at_once()).min(self.steps.len());
+		let batch = &self.steps[self.position..end];
+
+		let mut lifted: HashMap<usize, usize> = HashMap::new();
+		batch.iter().for_each(|step| {
+			*lifted.entry(step.source).or_default() += step.amount;
+			lifted.entry(step.target).or_default();
+		});
+		let restore = lifted
+			.into_iter()
+			.map(|(i, amount)| {
+				let stack = self.stacks.get(&i).cloned().unwrap_or_default();
+				let keep = stack.len().saturating_sub(amount);
+				(i, keep, stack[keep..].to_vec())
+			})
+			.collect::<Vec<_>>();
+
+		self.log.push(Reversal {
+			crane: self.crane.clone(),
+			steps: batch.len(),
+			restore,
+		});
+		self.crane.operate(&mut self.stacks, batch);
+		self.position = end;
+
+		true
+	}
+
+	fn back(&mut self) -> bool {
+		let Some(reversal) = self.log.pop() else {
+			return false;
+		};
+
+		for (i, keep, crates) in reversal.restore {
+			let stack = self.stacks.entry(i).or_default();
+			stack.truncate(keep);
+			stack.extend(crates);
+		}
+		self.crane = reversal.crane;
+		self.position -= reversal.steps;
+
+		true
+	}
+
+	// moves to the state after `index` steps, or the closest batch boundary past it
+	fn seek(&mut self, index: usize) {
+		while self.position > index && self.back() {}
+		while self.position < index && self.forward() {}
+	}
+
+	// first position at which `predicate` holds, scanning forward from the start
+	fn first_where<F>(&mut self, predicate: F) -> Option<usize>
+	where
+		F: Fn(&Stacks) -> bool,
+	{
+		self.seek(0);
+		loop {
+			if predicate(self.stacks()) {
+				return Some(self.position());
+			}
+			if !self.forward() {
+				return None;
+			}
+		}
+	}
+}
+
+fn on_top(c: char) -> impl Fn(&Stacks) -> bool {
+	move |stacks| stacks.values().any(|s| s.last() == Some(&c))
 }
 
 #[cfg(test)]
@@ -178,6 +750,305 @@ mod tests {
 		assert_eq!(steps, expected_steps);
 	}
 
+	#[test]
+	fn test_render_stacks() {
+		let raw_stacks = concat!(
+			"    [D]    \n",
+			"[N] [C]    \n",
+			"[Z] [M] [P]\n",
+			" 1   2   3 \n",
+		);
+
+		let stacks = parse_stacks(raw_stacks);
+		assert_eq!(render_stacks(&stacks), raw_stacks);
+
+		let stacks = HashMap::from([
+			(1, vec!['A']),
+			(2, vec!['B', 'C', 'D', 'E']),
+			(3, vec!['F', 'G']),
+			(4, vec!['H', 'I', 'J']),
+			(5, vec!['K']),
+		]);
+		assert_eq!(parse_stacks(&render_stacks(&stacks)), stacks);
+	}
+
+	#[test]
+	fn test_cranes() {
+		let stacks = HashMap::from([
+			(1, vec!['A', 'B', 'C', 'D', 'E']),
+			(2, vec![]),
+			(3, vec!['F']),
+		]);
+		let steps = vec![
+			Step {
+				amount: 3,
+				source: 1,
+				target: 2,
+			},
+			Step {
+				amount: 1,
+				source: 1,
+				target: 3,
+			},
+			Step {
+				amount: 2,
+				source: 2,
+				target: 3,
+			},
+		];
+
+		let result = simulate(&stacks, &steps, &mut CrateMover9000);
+		assert_eq!(result[&1], vec!['A']);
+		assert_eq!(result[&2], vec!['E']);
+		assert_eq!(result[&3], vec!['F', 'B', 'C', 'D']);
+
+		let result = simulate(&stacks, &steps, &mut CrateMover9001);
+		assert_eq!(result[&2], vec!['C']);
+		assert_eq!(result[&3], vec!['F', 'B', 'D', 'E']);
+
+		let result = simulate(&stacks, &steps, &mut LimitedCrane::new(2).unwrap());
+		assert_eq!(result[&2], vec!['D']);
+		assert_eq!(result[&3], vec!['F', 'B', 'E', 'C']);
+		assert!(LimitedCrane::new(0).is_none());
+		assert!(MultiCrane::new(CrateMover9001, 0).is_none());
+
+		let result = simulate(&stacks, &steps, &mut FlippingCrane::default());
+		assert_eq!(result[&2], vec!['C']);
+		assert_eq!(result[&3], vec!['F', 'B', 'D', 'E']);
+
+		let mut crane = FlippingCrane { flip_next: true };
+		let result = simulate(&stacks, &steps, &mut crane);
+		assert_eq!(result[&2], vec!['E']);
+		assert_eq!(result[&3], vec!['F', 'B', 'C', 'D']);
+
+		let steps = vec![
+			Step {
+				amount: 1,
+				source: 1,
+				target: 3,
+			},
+			Step {
+				amount: 1,
+				source: 3,
+				target: 2,
+			},
+		];
+
+		let result = simulate(&stacks, &steps, &mut CrateMover9000);
+		assert_eq!(result[&2], vec!['E']);
+		assert_eq!(result[&3], vec!['F']);
+
+		let mut crane = MultiCrane::new(CrateMover9001, 2).unwrap();
+		let result = simulate(&stacks, &steps, &mut crane);
+		assert_eq!(result[&1], vec!['A', 'B', 'C', 'D']);
+		assert_eq!(result[&2], vec!['F']);
+		assert_eq!(result[&3], vec!['E']);
+	}
+
+	#[test]
+	fn test_replay() {
+		let raw = concat!(
+			"    [D]    \n",
+			"[N] [C]    \n",
+			"[Z] [M] [P]\n",
+			" 1   2   3 \n",
+			"\n",
+			"move 1 from 2 to 1\n",
+			"move 3 from 1 to 3\n",
+			"move 2 from 2 to 1\n",
+			"move 1 from 1 to 2\n",
+		);
+
+		let (stacks, steps) = parse_input(raw);
+		let mut replay = Replay::new(&stacks, &steps, CrateMover9000);
+
+		while replay.forward() {}
+		assert_eq!(replay.position(), 4);
+		assert_eq!(top_crates(replay.stacks()), "CMZ");
+
+		replay.seek(1);
+		assert_eq!(
+			render_stacks(replay.stacks()),
+			concat!(
+				"[D]        \n",
+				"[N] [C]    \n",
+				"[Z] [M] [P]\n",
+				" 1   2   3 \n",
+			)
+		);
+
+		while replay.back() {}
+		assert_eq!(replay.position(), 0);
+		assert_eq!(replay.stacks(), &stacks);
+
+		assert_eq!(replay.first_where(on_top('Z')), Some(2));
+		assert_eq!(replay.position(), 2);
+		assert_eq!(replay.first_where(on_top('X')), None);
+
+		// N starts on top and is buried by the first step, for good
+		assert_eq!(replay.first_where(on_top('N')), Some(0));
+
+		// C comes up with the first step, is buried by the third, then comes back up
+		assert_eq!(replay.first_where(on_top('C')), Some(1));
+		replay.seek(3);
+		assert!(!on_top('C')(replay.stacks()));
+
+		let mut replay = Replay::new(&stacks, &steps, FlippingCrane::default());
+		replay.seek(4);
+		let end = replay.stacks().clone();
+		replay.seek(1);
+		replay.seek(4);
+		assert_eq!(replay.stacks(), &end);
+
+		let crane = MultiCrane::new(CrateMover9001, 3).unwrap();
+		let rotation = (1..=3)
+			.map(|i| Step {
+				amount: 1,
+				source: i,
+				target: i % 3 + 1,
+			})
+			.collect::<Vec<_>>();
+		let mut replay = Replay::new(&stacks, &rotation, crane);
+		replay.seek(1);
+		assert_eq!(replay.position(), 3);
+		assert_eq!(top_crates(replay.stacks()), "PND");
+		replay.back();
+		assert_eq!(replay.stacks(), &stacks);
+	}
+
+	#[test]
+	fn test_validate() {
+		let raw = concat!(
+			"[A]        \n",
+			"[B]     [C]\n",
+			" 1   2   3 \n",
+			"\n",
+			"move 1 from 1 to 2\n",
+			"move 2 from 2 to 3\n",
+			"move 2 from 3 to 4\n",
+		);
+
+		let (stacks, steps) = parse_input(raw);
+		assert!(stacks[&2].is_empty());
+
+		assert_eq!(validate(&stacks, &steps[..1], 1), Ok(()));
+		assert_eq!(
+			validate(&stacks, &steps, 1),
+			Err(InvalidStep {
+				index: 1,
+				error: StepError::NotEnoughCrates {
+					available: 1,
+					requested: 2,
+				},
+			})
+		);
+
+		let steps = vec![
+			Step {
+				amount: 1,
+				source: 1,
+				target: 3,
+			},
+			Step {
+				amount: 2,
+				source: 3,
+				target: 4,
+			},
+		];
+		assert_eq!(
+			validate(&stacks, &steps, 1),
+			Err(InvalidStep {
+				index: 1,
+				error: StepError::UnknownStack(4),
+			})
+		);
+		let steps = vec![
+			Step {
+				amount: 1,
+				source: 1,
+				target: 3,
+			},
+			Step {
+				amount: 2,
+				source: 3,
+				target: 2,
+			},
+		];
+		assert_eq!(validate(&stacks, &steps, 1), Ok(()));
+		assert_eq!(
+			validate(&stacks, &steps, 2),
+			Err(InvalidStep {
+				index: 1,
+				error: StepError::NotEnoughCrates {
+					available: 1,
+					requested: 2,
+				},
+			})
+		);
+	}
+
+	#[test]
+	fn test_plan() {
+		let raw_stacks = concat!(
+			"    [D]    \n",
+			"[N] [C]    \n",
+			"[Z] [M] [P]\n",
+			" 1   2   3 \n",
+		);
+		let stacks = parse_stacks(raw_stacks);
+
+		for top_row in ["NDP", "NCP", "MCD", "CMZ", "ZNM", "PZD"] {
+			let steps = plan(&stacks, top_row, &CrateMover9001).unwrap();
+			assert_eq!(validate(&stacks, &steps, 1), Ok(()));
+			assert!(steps.len() <= 2 * top_row.len());
+
+			let result = simulate(&stacks, &steps, &mut CrateMover9001);
+			assert_eq!(top_crates(&result), top_row);
+		}
+
+		assert_eq!(plan(&stacks, "NDP", &CrateMover9000), Some(vec![]));
+
+		let steps = plan(&stacks, "DCP", &CrateMover9000).unwrap();
+		let rendered = steps.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+		assert_eq!(rendered, vec!["move 1 from 2 to 1"]);
+
+		assert_eq!(plan(&stacks, "NDPZ", &CrateMover9001), None);
+		assert_eq!(plan(&stacks, "XDP", &CrateMover9001), None);
+		assert_eq!(plan(&stacks, "NNP", &CrateMover9001), None);
+	}
+
+	#[test]
+	fn test_dig() {
+		let raw_stacks = concat!(
+			"    [D]    \n",
+			"[N] [C]    \n",
+			"[Z] [M] [P]\n",
+			" 1   2   3 \n",
+		);
+		let stacks = parse_stacks(raw_stacks);
+		let crates = ['N', 'Z', 'D', 'C', 'M', 'P'];
+
+		// every row of three different crates, including the ones buried at the bottom
+		for a in crates {
+			for b in crates.iter().filter(|b| **b != a) {
+				for c in crates.iter().filter(|c| **c != a && *c != b) {
+					let top_row = [a, *b, *c].iter().collect::<String>();
+					let steps = dig(&stacks, &[a, *b, *c]);
+					assert_eq!(validate(&stacks, &steps, 1), Ok(()));
+
+					let result = simulate(&stacks, &steps, &mut CrateMover9000);
+					assert_eq!(top_crates(&result), top_row);
+				}
+			}
+		}
+
+		// the same crate wanted twice needs two of them
+		let stacks = parse_stacks(concat!("[A]        \n", "[A] [B] [C]\n", " 1   2   3 \n"));
+		let steps = dig(&stacks, &['C', 'A', 'A']);
+		let result = simulate(&stacks, &steps, &mut CrateMover9000);
+		assert_eq!(top_crates(&result), "CAA");
+	}
+
 	#[test]
 	fn test_part01() {
 		let raw = concat!(
@@ -1,6 +1,12 @@
+use std::collections::HashMap;
 use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
 use std::path::Path;
 
+extern crate test;
+
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
 	let input_path = format!("src/{module_name}/input.txt");
@@ -12,6 +18,21 @@ pub fn run() {
 
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
+
+	let file = File::open(path).expect("Could not open input.txt");
+	let mut packet_markers = 0;
+	for marker in MarkerDetector::new(file, 4).unwrap() {
+		marker.expect("Could not read input.txt");
+		packet_markers += 1;
+	}
+	println!("start-of-packet markers {:?}", packet_markers);
+
+	let frames = decode(input.as_bytes(), &FrameConfig::default()).collect::<Vec<_>>();
+	let mut stats = frame_stats(&frames).into_iter().collect::<Vec<_>>();
+	stats.sort_by_key(|(kind, _)| *kind);
+	stats
+		.iter()
+		.for_each(|(kind, s)| println!("{:?} frames {:?}", kind, s));
 }
 
 fn part01(input: &str) -> usize {
@@ -23,23 +44,333 @@ fn part02(input: &str) -> usize {
 }
 
 fn find_consec_uniq(input: &str, uniq_len: usize) -> usize {
-	let chars = input.chars().collect::<Vec<_>>();
-	let consec_uniq = chars
-		.windows(uniq_len)
-		.enumerate()
-		.find(|(_i, cs)| {
-			let mut csc = cs.to_vec();
-			csc.sort_unstable();
-			csc.dedup();
-			csc.len() == cs.len()
+	MarkerDetector::new(input.as_bytes(), uniq_len)
+		.unwrap()
+		.next()
+		.unwrap()
+		.unwrap()
+}
+
+// yields the number of bytes read each time the last `window_len` bytes
+// are all different, keeping a count per byte value instead of the window
+struct MarkerDetector<R: Read> {
+	reader: R,
+	buffer: Vec<u8>,
+	filled: usize,
+	cursor: usize,
+	window_len: usize,
+	history: Vec<u8>,
+	counts: [usize; 256],
+	repeated: usize,
+	position: usize,
+}
+
+impl<R: Read> MarkerDetector<R> {
+	// no detector for an empty window, which every position would be a marker for
+	fn new(reader: R, window_len: usize) -> Option<Self> {
+		if window_len == 0 {
+			return None;
+		}
+
+		Some(MarkerDetector {
+			reader,
+			buffer: vec![0; 64 * 1024],
+			filled: 0,
+			cursor: 0,
+			window_len,
+			history: vec![0; window_len],
+			counts: [0; 256],
+			repeated: 0,
+			position: 0,
+		})
+	}
+
+	fn push(&mut self, byte: u8) -> bool {
+		let slot = self.position % self.window_len;
+
+		if self.position >= self.window_len {
+			let old = self.history[slot] as usize;
+			self.counts[old] -= 1;
+			if self.counts[old] == 1 {
+				self.repeated -= 1;
+			}
+		}
+
+		self.history[slot] = byte;
+		self.counts[byte as usize] += 1;
+		if self.counts[byte as usize] == 2 {
+			self.repeated += 1;
+		}
+		self.position += 1;
+
+		self.position >= self.window_len && self.repeated == 0
+	}
+}
+
+impl<R: Read> Iterator for MarkerDetector<R> {
+	type Item = io::Result<usize>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			while self.cursor < self.filled {
+				let byte = self.buffer[self.cursor];
+				self.cursor += 1;
+				if self.push(byte) {
+					return Some(Ok(self.position));
+				}
+			}
+
+			match self.reader.read(&mut self.buffer) {
+				Ok(0) => return None,
+				Ok(n) => {
+					self.filled = n;
+					self.cursor = 0;
+				}
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+				Err(e) => return Some(Err(e)),
+			}
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+enum MarkerKind {
+	Packet,
+	Message,
+}
+
+struct FrameConfig {
+	packet_len: usize,
+	message_len: usize,
+}
+
+impl Default for FrameConfig {
+	fn default() -> Self {
+		FrameConfig {
+			packet_len: 4,
+			message_len: 14,
+		}
+	}
+}
+
+impl FrameConfig {
+	fn markers(&self) -> [(MarkerKind, usize); 2] {
+		[
+			(MarkerKind::Packet, self.packet_len),
+			(MarkerKind::Message, self.message_len),
+		]
+	}
+}
+
+#[derive(Debug, PartialEq)]
+struct Frame<'a> {
+	kind: MarkerKind,
+	// position right after the marker, where the payload starts
+	offset: usize,
+	marker: &'a [u8],
+	payload: &'a [u8],
+}
+
+// every marker kind frames the stream on its own: a marker starts a frame whose
+// payload runs up to the next marker of the same kind, and the search for that
+// next marker starts over after the current one
+fn decode<'a>(data: &'a [u8], config: &FrameConfig) -> impl Iterator<Item = Frame<'a>> {
+	let mut frames = config
+		.markers()
+		.into_iter()
+		.flat_map(|(kind, len)| {
+			let mut next_allowed = len;
+			// a kind with an empty marker frames nothing
+			let offsets = MarkerDetector::new(data, len)
+				.into_iter()
+				.flatten()
+				.map(|m| m.unwrap())
+				.filter(|m| {
+					if *m < next_allowed {
+						return false;
+					}
+					next_allowed = m + len;
+					true
+				})
+				.collect::<Vec<_>>();
+
+			let ends = offsets
+				.iter()
+				.skip(1)
+				.map(|next| next - len)
+				.chain([data.len()])
+				.collect::<Vec<_>>();
+
+			offsets
+				.into_iter()
+				.zip(ends)
+				.map(move |(offset, end)| Frame {
+					kind,
+					offset,
+					marker: &data[(offset - len)..offset],
+					payload: &data[offset..end],
+				})
 		})
-		.unwrap();
-	consec_uniq.0 + uniq_len
+		.collect::<Vec<_>>();
+
+	frames.sort_by_key(|f| (f.offset, f.kind));
+	frames.into_iter()
+}
+
+#[derive(Debug, PartialEq, Default)]
+struct FrameStats {
+	frames: usize,
+	payload_bytes: usize,
+	longest_payload: usize,
+	// bytes before the first marker of this kind
+	unframed_bytes: usize,
+}
+
+fn frame_stats(frames: &[Frame]) -> HashMap<MarkerKind, FrameStats> {
+	let mut stats: HashMap<MarkerKind, FrameStats> = HashMap::new();
+
+	for frame in frames {
+		let is_first = !stats.contains_key(&frame.kind);
+		let s = stats.entry(frame.kind).or_default();
+		if is_first {
+			s.unframed_bytes = frame.offset - frame.marker.len();
+		}
+		s.frames += 1;
+		s.payload_bytes += frame.payload.len();
+		s.longest_payload = s.longest_payload.max(frame.payload.len());
+	}
+
+	stats
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::test_input::numbers;
+	use test::Bencher;
+
+	// hands out one byte per read to exercise refilling the buffer
+	struct Trickle<'a>(&'a [u8]);
+
+	impl Read for Trickle<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			match self.0.split_first() {
+				Some((byte, rest)) if !buf.is_empty() => {
+					buf[0] = *byte;
+					self.0 = rest;
+					Ok(1)
+				}
+				_ => Ok(0),
+			}
+		}
+	}
+
+	fn generate_stream(len: usize) -> Vec<u8> {
+		let mut next = numbers(7);
+		(0..len).map(|_| b'a' + (next() % 26) as u8).collect()
+	}
+
+	#[test]
+	fn test_marker_detector() {
+		let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+		let markers = MarkerDetector::new(input.as_bytes(), 4)
+			.unwrap()
+			.map(|m| m.unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(markers[..4], [7, 8, 9, 10]);
+
+		let trickled = MarkerDetector::new(Trickle(input.as_bytes()), 4)
+			.unwrap()
+			.map(|m| m.unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(trickled, markers);
+
+		let stream = generate_stream(5000);
+		for window_len in [1, 4, 14] {
+			let expected = stream
+				.windows(window_len)
+				.enumerate()
+				.filter(|(_, w)| {
+					let mut w = w.to_vec();
+					w.sort_unstable();
+					w.dedup();
+					w.len() == window_len
+				})
+				.map(|(i, _)| i + window_len)
+				.collect::<Vec<_>>();
+
+			let markers = MarkerDetector::new(&stream[..], window_len)
+				.unwrap()
+				.map(|m| m.unwrap())
+				.collect::<Vec<_>>();
+			assert_eq!(markers, expected);
+		}
+
+		assert_eq!(MarkerDetector::new(&b"aaaa"[..], 2).unwrap().count(), 0);
+		assert!(MarkerDetector::new(&b"abcd"[..], 0).is_none());
+	}
+
+	#[test]
+	fn test_decode() {
+		let data = b"aabcdeeefgxyz";
+		let config = FrameConfig {
+			packet_len: 2,
+			message_len: 4,
+		};
+
+		let frames = decode(data, &config)
+			.map(|f| {
+				(
+					f.kind,
+					f.offset,
+					String::from_utf8_lossy(f.payload).to_string(),
+				)
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			frames,
+			vec![
+				(MarkerKind::Packet, 3, String::from("")),
+				(MarkerKind::Packet, 5, String::from("ee")),
+				(MarkerKind::Message, 5, String::from("ee")),
+				(MarkerKind::Packet, 9, String::from("")),
+				(MarkerKind::Packet, 11, String::from("")),
+				(MarkerKind::Message, 11, String::from("yz")),
+				(MarkerKind::Packet, 13, String::from("")),
+			]
+		);
+
+		let frames = decode(data, &config).collect::<Vec<_>>();
+		let stats = frame_stats(&frames);
+		assert_eq!(
+			stats[&MarkerKind::Message],
+			FrameStats {
+				frames: 2,
+				payload_bytes: 4,
+				longest_payload: 2,
+				unframed_bytes: 1,
+			}
+		);
+		assert_eq!(stats[&MarkerKind::Packet].frames, 5);
+		assert_eq!(stats[&MarkerKind::Packet].unframed_bytes, 1);
+
+		let no_packets = FrameConfig {
+			packet_len: 0,
+			..config
+		};
+		let kinds = decode(data, &no_packets)
+			.map(|f| f.kind)
+			.collect::<Vec<_>>();
+		assert_eq!(kinds, vec![MarkerKind::Message, MarkerKind::Message]);
+
+		let input = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
+		let first_message = decode(input.as_bytes(), &FrameConfig::default())
+			.find(|f| f.kind == MarkerKind::Message)
+			.unwrap();
+		assert_eq!(first_message.offset, part02(input));
+		assert_eq!(first_message.marker, b"qmgbljsphdztnv");
+		assert_eq!(first_message.payload, b"jfqwrcgsmlb");
+	}
 
 	#[test]
 	fn test_part01() {
@@ -86,4 +417,10 @@ mod tests {
 		let result = part02(input);
 		assert_eq!(result, 26);
 	}
+
+	#[bench]
+	fn bench_marker_detector(b: &mut Bencher) {
+		let stream = generate_stream(1_000_000);
+		b.iter(|| MarkerDetector::new(&stream[..], 14).unwrap().count());
+	}
 }
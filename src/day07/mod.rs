@@ -1,6 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
@@ -13,6 +15,75 @@ pub fn run() {
 
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
+
+	let shell = Shell::replay(&input);
+	shell
+		.warnings
+		.iter()
+		.for_each(|w| println!("line {}: {:?}", w.line, w.kind));
+
+	// too many files to try every combination of, so settle for a good plan
+	let options = DeletionOptions {
+		include_dirs: false,
+		include_files: true,
+		max_picks: Some(100_000),
+		..DeletionOptions::default()
+	};
+	if let Some(plan) = plan_deletion(&shell.fs, &options) {
+		let paths = plan.nodes.iter().map(|id| shell.fs.path(*id));
+		let exact = if plan.exact {
+			""
+		} else {
+			" (search cut short)"
+		};
+		println!(
+			"deleting files only{} {:?}",
+			exact,
+			paths.collect::<Vec<_>>()
+		);
+	}
+
+	// a directory on disk is transcribed on request, e.g. `TRANSCRIBE_DIR=src cargo run day07`,
+	// and the transcript has to replay to the same tree
+	if let Some(dir) = env::var_os("TRANSCRIBE_DIR") {
+		match FileSystem::from_dir(Path::new(&dir)) {
+			Ok((disk, skipped)) => {
+				skipped
+					.iter()
+					.for_each(|p| println!("skipped {}", p.display()));
+				for order in [Traversal::DepthFirst, Traversal::BreadthFirst] {
+					let options = TranscriptOptions {
+						order,
+						relist: true,
+					};
+					let replayed = Shell::replay(&transcript(&disk, &options));
+					println!(
+						"{:?} transcript: {} warnings, same tree {}",
+						order,
+						replayed.warnings.len(),
+						replayed.fs.tree() == disk.tree()
+					);
+				}
+			}
+			Err(e) => println!("Could not read {}: {}", Path::new(&dir).display(), e),
+		}
+	}
+
+	// lines of a file are run as queries on request, e.g.
+	// `echo "du -d 1" | QUERY_FILE=/dev/stdin cargo run day07`
+	if let Some(file) = env::var_os("QUERY_FILE") {
+		match fs::read_to_string(&file) {
+			Ok(commands) => {
+				for command in commands.lines() {
+					match query(&shell.fs, command) {
+						Ok(output) => print!("{}", output),
+						Err(e) => println!("{}", e),
+					}
+				}
+			}
+			Err(e) => println!("Could not read {}: {}", Path::new(&file).display(), e),
+		}
+	}
 }
 
 fn part01(input: &str) -> usize {
@@ -25,81 +96,791 @@ fn part01(input: &str) -> usize {
 }
 
 fn part02(input: &str) -> usize {
-	let dir_usage = du(input);
+	let fs = parse_transcript(input);
+	let plan = plan_deletion(&fs, &DeletionOptions::default()).unwrap();
 
-	let total_size = dir_usage.get("/").unwrap();
-	let space_left = 70000000 - total_size;
-	let space_needed = 30000000 - space_left;
+	plan.freed
+}
 
-	let mut dirs_to_delete = dir_usage
-		.iter()
-		.filter_map(|(_, size)| {
-			if *size >= space_needed {
-				Some(size)
-			} else {
-				None
+fn du(input: &str) -> HashMap<String, usize> {
+	let fs = parse_transcript(input);
+	let sizes = fs.sizes();
+
+	fs.dirs().map(|d| (fs.path(d), sizes[d])).collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum NodeKind {
+	Dir(Vec<usize>),
+	File(usize),
+}
+
+#[derive(Debug)]
+struct Node {
+	name: String,
+	parent: Option<usize>,
+	kind: NodeKind,
+}
+
+// nodes live in one list and refer to each other by index, the root is always 0
+#[derive(Debug)]
+struct FileSystem {
+	nodes: Vec<Node>,
+}
+
+const ROOT: usize = 0;
+
+impl FileSystem {
+	fn new() -> Self {
+		FileSystem {
+			nodes: vec![Node {
+				name: String::from("/"),
+				parent: None,
+				kind: NodeKind::Dir(vec![]),
+			}],
+		}
+	}
+
+	fn children(&self, dir: usize) -> &[usize] {
+		match &self.nodes[dir].kind {
+			NodeKind::Dir(children) => children,
+			NodeKind::File(_) => &[],
+		}
+	}
+
+	fn child(&self, dir: usize, name: &str) -> Option<usize> {
+		self.children(dir)
+			.iter()
+			.find(|c| self.nodes[**c].name == name)
+			.copied()
+	}
+
+	fn add(&mut self, dir: usize, name: &str, kind: NodeKind) -> usize {
+		let id = self.nodes.len();
+		self.nodes.push(Node {
+			name: String::from(name),
+			parent: Some(dir),
+			kind,
+		});
+		if let NodeKind::Dir(children) = &mut self.nodes[dir].kind {
+			children.push(id);
+		}
+		id
+	}
+
+	fn add_dir(&mut self, dir: usize, name: &str) -> usize {
+		match self.child(dir, name) {
+			Some(existing) => existing,
+			None => self.add(dir, name, NodeKind::Dir(vec![])),
+		}
+	}
+
+	fn add_file(&mut self, dir: usize, name: &str, size: usize) -> usize {
+		match self.child(dir, name) {
+			Some(existing) => existing,
+			None => self.add(dir, name, NodeKind::File(size)),
+		}
+	}
+
+	fn is_dir(&self, id: usize) -> bool {
+		matches!(self.nodes[id].kind, NodeKind::Dir(_))
+	}
+
+	fn dirs(&self) -> impl Iterator<Item = usize> + '_ {
+		(0..self.nodes.len()).filter(|i| self.is_dir(*i))
+	}
+
+	fn path(&self, id: usize) -> String {
+		let mut names = vec![];
+		let mut cursor = id;
+		while let Some(parent) = self.nodes[cursor].parent {
+			names.push(self.nodes[cursor].name.as_str());
+			cursor = parent;
+		}
+		names.reverse();
+
+		String::from("/") + &names.join("/")
+	}
+
+	// total size of every node, indexed like `nodes`, in one post-order walk
+	fn sizes(&self) -> Vec<usize> {
+		let mut sizes = vec![0; self.nodes.len()];
+		let mut stack = vec![(ROOT, false)];
+
+		while let Some((id, visited)) = stack.pop() {
+			match &self.nodes[id].kind {
+				NodeKind::File(size) => sizes[id] = *size,
+				NodeKind::Dir(children) if visited => {
+					sizes[id] = children.iter().map(|c| sizes[*c]).sum();
+				}
+				NodeKind::Dir(children) => {
+					stack.push((id, true));
+					stack.extend(children.iter().map(|c| (*c, false)));
+				}
 			}
-		})
-		.collect::<Vec<_>>();
+		}
 
-	dirs_to_delete.sort();
-	*dirs_to_delete[0]
+		sizes
+	}
 }
 
-fn du(input: &str) -> HashMap<String, usize> {
-	let mut lines = input.lines();
-	let mut file_list = HashMap::new();
-	let mut current_path = String::from("");
-	let mut dir_list = vec![String::from("/")];
-	let mut dir_usage = HashMap::new();
-	while let Some(line) = lines.next() {
-		if line.starts_with("$ cd /") {
-			current_path = String::from("/");
-		} else if line.starts_with("$ cd ..") {
-			let last_dirname = current_path
-				.trim_end_matches('/')
-				.split('/')
-				.last()
-				.unwrap();
-			current_path = String::from(
-				current_path
-					.trim_end_matches("/")
-					.trim_end_matches(last_dirname),
+impl FileSystem {
+	fn depth(&self, id: usize) -> usize {
+		let mut depth = 0;
+		let mut cursor = id;
+		while let Some(parent) = self.nodes[cursor].parent {
+			depth += 1;
+			cursor = parent;
+		}
+		depth
+	}
+
+	// every node below the root, parents before their children
+	fn walk(&self) -> Vec<usize> {
+		let mut order = vec![];
+		let mut stack = vec![ROOT];
+		while let Some(id) = stack.pop() {
+			order.push(id);
+			stack.extend(self.children(id).iter().rev());
+		}
+		order
+	}
+
+	fn tree(&self) -> String {
+		let sizes = self.sizes();
+
+		self.walk()
+			.into_iter()
+			.map(|id| {
+				let indent = "  ".repeat(self.depth(id));
+				let kind = if self.is_dir(id) { "dir" } else { "file" };
+				format!(
+					"{}- {} ({}, size={})\n",
+					indent, self.nodes[id].name, kind, sizes[id]
+				)
+			})
+			.collect()
+	}
+
+	// directories at most `max_depth` below the root in the order `du` prints
+	// them, each one after everything below it
+	fn disk_usage(&self, max_depth: usize) -> Vec<(String, usize)> {
+		let sizes = self.sizes();
+		let mut dirs = vec![];
+		// directories still to visit, and whether their children are done
+		let mut stack = vec![(ROOT, false)];
+		while let Some((id, done)) = stack.pop() {
+			if done {
+				dirs.push((self.path(id), sizes[id]));
+				continue;
+			}
+			stack.push((id, true));
+			stack.extend(
+				self.children(id)
+					.iter()
+					.rev()
+					.filter(|c| self.is_dir(**c) && self.depth(**c) <= max_depth)
+					.map(|c| (*c, false)),
 			);
-		} else if line.starts_with("$ cd") {
-			current_path = current_path.to_owned() + &line[5..] + "/";
-		} else if line.starts_with("$ ls") {
+		}
+		dirs
+	}
+
+	fn find(&self, query: &FindQuery) -> Vec<usize> {
+		let sizes = self.sizes();
+
+		self.walk()
+			.into_iter()
+			.filter(|id| {
+				let name_matches = match &query.name {
+					Some(pattern) => glob_matches(pattern, &self.nodes[*id].name),
+					None => true,
+				};
+				let above_min = query.min_size.map_or(true, |min| sizes[*id] > min);
+				let below_max = query.max_size.map_or(true, |max| sizes[*id] < max);
+				name_matches && above_min && below_max
+			})
+			.collect()
+	}
+
+	fn largest_files(&self, n: usize) -> Vec<usize> {
+		let mut files = (0..self.nodes.len())
+			.filter_map(|id| match self.nodes[id].kind {
+				NodeKind::File(size) => Some((size, id)),
+				NodeKind::Dir(_) => None,
+			})
+			.collect::<Vec<_>>();
+		files.sort_by(|a, b| {
+			b.0.cmp(&a.0)
+				.then_with(|| self.path(a.1).cmp(&self.path(b.1)))
+		});
+
+		files.into_iter().take(n).map(|(_, id)| id).collect()
+	}
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct FindQuery {
+	name: Option<String>,
+	min_size: Option<usize>,
+	max_size: Option<usize>,
+}
+
+// `*` matches any run of characters and `?` any single one
+fn glob_matches(pattern: &str, name: &str) -> bool {
+	let pattern = pattern.chars().collect::<Vec<_>>();
+	let name = name.chars().collect::<Vec<_>>();
+	let (mut p, mut n) = (0, 0);
+	let mut last_star = None;
+
+	while n < name.len() {
+		if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+			p += 1;
+			n += 1;
+		} else if p < pattern.len() && pattern[p] == '*' {
+			last_star = Some((p, n));
+			p += 1;
+		} else if let Some((star_p, star_n)) = last_star {
+			p = star_p + 1;
+			n = star_n + 1;
+			last_star = Some((star_p, star_n + 1));
 		} else {
-			if line.starts_with("dir") {
-				let (_, dir_name) = line.split_once(' ').unwrap();
-				let dir_path = current_path.clone() + dir_name;
-				dir_list.push(dir_path);
+			return false;
+		}
+	}
+
+	pattern[p..].iter().all(|c| *c == '*')
+}
+
+// rounds up like `du -h` does, moving on to the next unit once rounding reaches it
+fn human_size(size: usize) -> String {
+	let units = ["K", "M", "G", "T"];
+	if size < 1024 {
+		return format!("{}", size);
+	}
+
+	let mut value = size as f64 / 1024.0;
+	let mut unit = 0;
+	loop {
+		let tenths = (value * 10.0).ceil() / 10.0;
+		if tenths < 10.0 {
+			return format!("{:.1}{}", tenths, units[unit]);
+		}
+		if value.ceil() < 1024.0 || unit == units.len() - 1 {
+			return format!("{:.0}{}", value.ceil(), units[unit]);
+		}
+		value /= 1024.0;
+		unit += 1;
+	}
+}
+
+// tree | du [-d N] | find [-name GLOB] [-size +N|-N] | largest N
+fn query(fs: &FileSystem, command: &str) -> Result<String, String> {
+	let args = command.split_whitespace().collect::<Vec<_>>();
+	let number = |s: &str| {
+		s.parse::<usize>()
+			.map_err(|_| format!("Not a number: {}", s))
+	};
+
+	match args[..] {
+		["tree"] => Ok(fs.tree()),
+		["du"] => query(fs, "du -d 0"),
+		["du", "-d", depth] => Ok(fs
+			.disk_usage(number(depth)?)
+			.into_iter()
+			.map(|(path, size)| format!("{}\t{}\n", human_size(size), path))
+			.collect()),
+		["find", ref options @ ..] => {
+			let mut find_query = FindQuery::default();
+			for option in options.chunks(2) {
+				match option {
+					["-name", pattern] => find_query.name = Some(String::from(*pattern)),
+					["-size", size] if size.starts_with('+') => {
+						find_query.min_size = Some(number(&size[1..])?)
+					}
+					["-size", size] if size.starts_with('-') => {
+						find_query.max_size = Some(number(&size[1..])?)
+					}
+					_ => return Err(format!("Unknown find option: {}", option.join(" "))),
+				}
+			}
+			Ok(fs
+				.find(&find_query)
+				.into_iter()
+				.map(|id| fs.path(id) + "\n")
+				.collect())
+		}
+		["largest", n] => {
+			let sizes = fs.sizes();
+			Ok(fs
+				.largest_files(number(n)?)
+				.into_iter()
+				.map(|id| format!("{}\t{}\n", human_size(sizes[id]), fs.path(id)))
+				.collect())
+		}
+		_ => Err(format!("Unknown query: {}", command)),
+	}
+}
+
+struct DeletionOptions {
+	capacity: usize,
+	required: usize,
+	include_dirs: bool,
+	include_files: bool,
+	// nothing at, above or below these paths gets deleted
+	protected: Vec<String>,
+	// stops each search after this many picks, settling for the best plan so far
+	max_picks: Option<usize>,
+}
+
+impl Default for DeletionOptions {
+	fn default() -> Self {
+		DeletionOptions {
+			capacity: 70000000,
+			required: 30000000,
+			include_dirs: true,
+			include_files: false,
+			protected: vec![],
+			max_picks: None,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+struct DeletionPlan {
+	nodes: Vec<usize>,
+	freed: usize,
+	// false when `max_picks` cut the search short, so there may be a better plan
+	exact: bool,
+}
+
+// fewest nodes that free enough space, and the least space among those, unless
+// `max_picks` ran out first, in which case there may be no plan at all
+fn plan_deletion(fs: &FileSystem, options: &DeletionOptions) -> Option<DeletionPlan> {
+	let sizes = fs.sizes();
+	let needed = (sizes[ROOT] + options.required).saturating_sub(options.capacity);
+	if needed == 0 {
+		return Some(DeletionPlan {
+			nodes: vec![],
+			freed: 0,
+			exact: true,
+		});
+	}
+
+	let is_protected = |id: usize| {
+		let path = fs.path(id);
+		options.protected.iter().any(|p| {
+			let within = |inner: &str, outer: &str| {
+				inner == outer
+					|| outer == "/" || inner.starts_with(outer) && inner[outer.len()..].starts_with('/')
+			};
+			within(p, &path) || within(&path, p)
+		})
+	};
+
+	let mut candidates = (0..fs.nodes.len())
+		.filter(|id| {
+			if fs.is_dir(*id) {
+				options.include_dirs
 			} else {
-				let (file_size, file_name) = line.split_once(' ').unwrap();
-				let file_size = file_size.parse::<usize>().unwrap();
-				let file_path = current_path.clone() + file_name;
-				file_list.insert(file_path, file_size);
+				options.include_files
+			}
+		})
+		.filter(|id| !is_protected(*id))
+		.collect::<Vec<_>>();
+	candidates.sort_by(|a, b| sizes[*b].cmp(&sizes[*a]));
+
+	let mut exact = true;
+	(1..=candidates.len()).find_map(|count| {
+		let mut search = DeletionSearch {
+			fs,
+			sizes: &sizes,
+			candidates: &candidates,
+			needed,
+			best: None,
+			budget: options.max_picks,
+			truncated: false,
+		};
+		search.pick(0, count, &mut vec![], 0);
+		exact &= !search.truncated;
+
+		search.best.map(|(nodes, freed)| DeletionPlan {
+			nodes,
+			freed,
+			exact,
+		})
+	})
+}
+
+struct DeletionSearch<'a> {
+	fs: &'a FileSystem,
+	sizes: &'a [usize],
+	candidates: &'a [usize],
+	needed: usize,
+	best: Option<(Vec<usize>, usize)>,
+	// picks left to try, if limited
+	budget: Option<usize>,
+	truncated: bool,
+}
+
+impl DeletionSearch<'_> {
+	fn pick(&mut self, from: usize, left: usize, picked: &mut Vec<usize>, freed: usize) {
+		match &mut self.budget {
+			Some(0) => {
+				self.truncated = true;
+				return;
 			}
+			Some(left) => *left -= 1,
+			None => {}
+		}
+
+		if let Some((_, best)) = &self.best {
+			if freed >= *best {
+				return;
+			}
+		}
+		if left == 0 {
+			if freed >= self.needed {
+				self.best = Some((picked.clone(), freed));
+			}
+			return;
+		}
+
+		for i in from..self.candidates.len() {
+			// candidates are sorted by size, so the next `left` are the most we can still
+			// add, and starting any later only adds less
+			let most = self.candidates[i..]
+				.iter()
+				.take(left)
+				.map(|id| self.sizes[*id])
+				.sum::<usize>();
+			if freed + most < self.needed {
+				break;
+			}
+
+			let id = self.candidates[i];
+			let overlaps = picked
+				.iter()
+				.any(|p| self.fs.is_within(id, *p) || self.fs.is_within(*p, id));
+			if overlaps {
+				continue;
+			}
+
+			picked.push(id);
+			self.pick(i + 1, left - 1, picked, freed + self.sizes[id]);
+			picked.pop();
 		}
 	}
-	for (file_path, file_size) in file_list {
-		dir_list
-			.iter()
-			.filter(|d| file_path.starts_with(*d))
-			.for_each(|d| {
-				dir_usage
-					.entry(d.clone())
-					.and_modify(|s| *s += file_size)
-					.or_insert(file_size);
-			});
+}
+
+impl FileSystem {
+	fn is_within(&self, id: usize, dir: usize) -> bool {
+		let mut cursor = Some(id);
+		while let Some(c) = cursor {
+			if c == dir {
+				return true;
+			}
+			cursor = self.nodes[c].parent;
+		}
+		false
 	}
-	dir_usage
+}
+
+fn parse_transcript(input: &str) -> FileSystem {
+	Shell::replay(input).fs
+}
+
+#[derive(Debug, PartialEq)]
+enum WarningKind {
+	UnsupportedCommand(String),
+	MalformedLine(String),
+	OutputWithoutLs,
+	CdAboveRoot,
+	CdIntoFile(String),
+	CdUnlisted(String),
+	ListedTwice(String),
+	ConflictingSize {
+		name: String,
+		previous: usize,
+		listed: usize,
+	},
+	ConflictingKind(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Warning {
+	line: usize,
+	kind: WarningKind,
+}
+
+// replays a transcript line by line, keeping the first version of anything
+// that is contradicted later and recording a warning instead
+struct Shell {
+	fs: FileSystem,
+	cwd: usize,
+	// names seen in the output of the current `ls`, if one is running
+	listing: Option<HashSet<String>>,
+	listed: HashSet<usize>,
+	warnings: Vec<Warning>,
+}
+
+impl Shell {
+	fn new() -> Self {
+		Shell {
+			fs: FileSystem::new(),
+			cwd: ROOT,
+			listing: None,
+			listed: HashSet::from([ROOT]),
+			warnings: vec![],
+		}
+	}
+
+	fn replay(input: &str) -> Self {
+		let mut shell = Shell::new();
+		input
+			.lines()
+			.enumerate()
+			.for_each(|(i, l)| shell.execute(i + 1, l));
+		shell
+	}
+
+	fn warn(&mut self, line: usize, kind: WarningKind) {
+		self.warnings.push(Warning { line, kind });
+	}
+
+	fn execute(&mut self, line_no: usize, line: &str) {
+		match line.strip_prefix("$ ") {
+			Some(command) => self.command(line_no, command),
+			None => self.output(line_no, line),
+		}
+	}
+
+	fn command(&mut self, line_no: usize, command: &str) {
+		self.listing = None;
+
+		let args = command.split_whitespace().collect::<Vec<_>>();
+		match args[..] {
+			["cd", "/"] => self.cwd = ROOT,
+			["cd", ".."] => match self.fs.nodes[self.cwd].parent {
+				Some(parent) => self.cwd = parent,
+				None => self.warn(line_no, WarningKind::CdAboveRoot),
+			},
+			["cd", name] => match self.fs.child(self.cwd, name) {
+				Some(id) if self.fs.is_dir(id) => {
+					if !self.listed.contains(&id) {
+						self.warn(line_no, WarningKind::CdUnlisted(String::from(name)));
+					}
+					self.cwd = id;
+				}
+				Some(_) => self.warn(line_no, WarningKind::CdIntoFile(String::from(name))),
+				None => {
+					self.warn(line_no, WarningKind::CdUnlisted(String::from(name)));
+					self.cwd = self.fs.add_dir(self.cwd, name);
+				}
+			},
+			["ls"] => self.listing = Some(HashSet::new()),
+			_ => self.warn(
+				line_no,
+				WarningKind::UnsupportedCommand(String::from(command)),
+			),
+		}
+	}
+
+	fn output(&mut self, line_no: usize, line: &str) {
+		let Some(listing) = self.listing.as_mut() else {
+			self.warn(line_no, WarningKind::OutputWithoutLs);
+			return;
+		};
+
+		let entry = match line.split_once(' ') {
+			Some(("dir", name)) => Some((name, None)),
+			Some((size, name)) => size.parse::<usize>().ok().map(|s| (name, Some(s))),
+			None => None,
+		};
+		let Some((name, size)) = entry else {
+			self.warn(line_no, WarningKind::MalformedLine(String::from(line)));
+			return;
+		};
+
+		if !listing.insert(String::from(name)) {
+			self.warn(line_no, WarningKind::ListedTwice(String::from(name)));
+			return;
+		}
+
+		let existing = self.fs.child(self.cwd, name);
+		let id = match (existing, size) {
+			(None, None) => self.fs.add_dir(self.cwd, name),
+			(None, Some(size)) => self.fs.add_file(self.cwd, name, size),
+			(Some(id), None) if self.fs.is_dir(id) => id,
+			(Some(id), Some(size)) if !self.fs.is_dir(id) => {
+				if let NodeKind::File(previous) = self.fs.nodes[id].kind {
+					if previous != size {
+						let name = String::from(name);
+						let conflict = WarningKind::ConflictingSize {
+							name,
+							previous,
+							listed: size,
+						};
+						self.warn(line_no, conflict);
+					}
+				}
+				id
+			}
+			(Some(id), _) => {
+				self.warn(line_no, WarningKind::ConflictingKind(String::from(name)));
+				id
+			}
+		};
+		self.listed.insert(id);
+	}
+}
+
+impl FileSystem {
+	// loads a directory on disk, entries sorted by name; symlinks and names the
+	// transcript format can't express (not UTF-8 or containing whitespace) are
+	// skipped and handed back alongside
+	fn from_dir(root: &Path) -> io::Result<(Self, Vec<PathBuf>)> {
+		let mut fs = FileSystem::new();
+		let mut skipped = vec![];
+		let mut stack = vec![(ROOT, root.to_path_buf())];
+
+		while let Some((dir, path)) = stack.pop() {
+			let mut entries = fs::read_dir(&path)?.collect::<io::Result<Vec<_>>>()?;
+			entries.sort_by_key(|e| e.file_name());
+
+			for entry in entries {
+				let name = match entry.file_name().into_string() {
+					Ok(name) if !name.contains(char::is_whitespace) => name,
+					_ => {
+						skipped.push(entry.path());
+						continue;
+					}
+				};
+
+				let file_type = entry.file_type()?;
+				if file_type.is_dir() {
+					let id = fs.add_dir(dir, &name);
+					stack.push((id, entry.path()));
+				} else if file_type.is_file() {
+					let size = entry.metadata()?.len() as usize;
+					fs.add_file(dir, &name, size);
+				} else {
+					skipped.push(entry.path());
+				}
+			}
+		}
+
+		Ok((fs, skipped))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Traversal {
+	// `cd` into each directory and back out with `cd ..`
+	DepthFirst,
+	// level by level, walking down from `cd /` to every directory
+	BreadthFirst,
+}
+
+struct TranscriptOptions {
+	order: Traversal,
+	// run `ls` every time a directory is entered, not just the first time
+	relist: bool,
+}
+
+// the `cd`/`ls` session that would have discovered `fs`
+fn transcript(fs: &FileSystem, options: &TranscriptOptions) -> String {
+	let mut lines = vec![];
+	let mut listed = HashSet::new();
+	let mut enter = |lines: &mut Vec<String>, dir: usize| {
+		if !listed.insert(dir) && !options.relist {
+			return;
+		}
+		lines.push(String::from("$ ls"));
+		lines.extend(fs.children(dir).iter().map(|c| match fs.nodes[*c].kind {
+			NodeKind::Dir(_) => format!("dir {}", fs.nodes[*c].name),
+			NodeKind::File(size) => format!("{} {}", size, fs.nodes[*c].name),
+		}));
+	};
+	let subdirs = |dir: usize| fs.children(dir).iter().filter(|c| fs.is_dir(**c)).copied();
+
+	match options.order {
+		Traversal::DepthFirst => {
+			lines.push(String::from("$ cd /"));
+			let mut stack = vec![(ROOT, false)];
+			while let Some((dir, leaving)) = stack.pop() {
+				if leaving {
+					// no need to climb back up once everything has been visited
+					if stack.iter().all(|(_, leaving)| *leaving) {
+						break;
+					}
+					lines.push(String::from("$ cd .."));
+					enter(&mut lines, fs.nodes[dir].parent.unwrap());
+					continue;
+				}
+
+				if dir != ROOT {
+					lines.push(format!("$ cd {}", fs.nodes[dir].name));
+				}
+				enter(&mut lines, dir);
+				stack.push((dir, true));
+				stack.extend(subdirs(dir).rev().map(|c| (c, false)));
+			}
+		}
+		Traversal::BreadthFirst => {
+			let mut queue = VecDeque::from([ROOT]);
+			while let Some(dir) = queue.pop_front() {
+				let mut path = vec![];
+				let mut cursor = dir;
+				while let Some(parent) = fs.nodes[cursor].parent {
+					path.push(cursor);
+					cursor = parent;
+				}
+
+				lines.push(String::from("$ cd /"));
+				enter(&mut lines, ROOT);
+				for id in path.into_iter().rev() {
+					lines.push(format!("$ cd {}", fs.nodes[id].name));
+					enter(&mut lines, id);
+				}
+				queue.extend(subdirs(dir));
+			}
+		}
+	}
+
+	lines.into_iter().map(|l| l + "\n").collect()
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	const INPUT: &str = concat!(
+		"$ cd /\n",
+		"$ ls\n",
+		"dir a\n",
+		"14848514 b.txt\n",
+		"8504156 c.dat\n",
+		"dir d\n",
+		"$ cd a\n",
+		"$ ls\n",
+		"dir e\n",
+		"29116 f\n",
+		"2557 g\n",
+		"62596 h.lst\n",
+		"$ cd e\n",
+		"$ ls\n",
+		"584 i\n",
+		"$ cd ..\n",
+		"$ cd ..\n",
+		"$ cd d\n",
+		"$ ls\n",
+		"4060174 j\n",
+		"8033020 d.log\n",
+		"5626152 d.ext\n",
+		"7214296 k\n",
+	);
+
 	#[test]
 	fn test_part01() {
 		let input = concat!(
@@ -133,6 +914,311 @@ mod tests {
 		assert_eq!(result, 95437);
 	}
 
+	#[test]
+	fn test_du() {
+		let input = concat!(
+			"$ cd /\n",
+			"$ ls\n",
+			"dir a\n",
+			"$ cd a\n",
+			"$ ls\n",
+			"dir b\n",
+			"dir bc\n",
+			"10 f\n",
+			"$ cd b\n",
+			"$ ls\n",
+			"100 g\n",
+			"$ cd ..\n",
+			"$ cd bc\n",
+			"$ ls\n",
+			"1000 h\n",
+		);
+
+		let result = du(input);
+		let expected = HashMap::from([
+			(String::from("/"), 1110),
+			(String::from("/a"), 1110),
+			(String::from("/a/b"), 100),
+			(String::from("/a/bc"), 1000),
+		]);
+		assert_eq!(result, expected);
+
+		let fs = parse_transcript(input);
+		let bc = fs.child(fs.child(ROOT, "a").unwrap(), "bc").unwrap();
+		let h = fs.child(bc, "h").unwrap();
+		assert_eq!(fs.path(h), "/a/bc/h");
+		assert_eq!(fs.nodes[h].kind, NodeKind::File(1000));
+		assert!(!fs.is_dir(h));
+	}
+
+	#[test]
+	fn test_shell_warnings() {
+		let input = concat!(
+			"$ cd /\n",
+			"$ ls\n",
+			"dir a\n",
+			"10 b\n",
+			"10 b\n",
+			"$ cd ..\n",
+			"$ cd c\n",
+			"$ cd ..\n",
+			"20 d\n",
+			"$ ls\n",
+			"11 b\n",
+			"dir b\n",
+			"$ rm -rf a\n",
+			"$ ls\n",
+			"what\n",
+			"$ cd b\n",
+			"$ cd a\n",
+		);
+
+		let shell = Shell::replay(input);
+		let warnings = shell
+			.warnings
+			.iter()
+			.map(|w| (w.line, &w.kind))
+			.collect::<Vec<_>>();
+
+		assert_eq!(
+			warnings,
+			vec![
+				(5, &WarningKind::ListedTwice(String::from("b"))),
+				(6, &WarningKind::CdAboveRoot),
+				(7, &WarningKind::CdUnlisted(String::from("c"))),
+				(9, &WarningKind::OutputWithoutLs),
+				(
+					11,
+					&WarningKind::ConflictingSize {
+						name: String::from("b"),
+						previous: 10,
+						listed: 11,
+					}
+				),
+				(12, &WarningKind::ListedTwice(String::from("b"))),
+				(
+					13,
+					&WarningKind::UnsupportedCommand(String::from("rm -rf a"))
+				),
+				(15, &WarningKind::MalformedLine(String::from("what"))),
+				(16, &WarningKind::CdIntoFile(String::from("b"))),
+			]
+		);
+
+		let b = shell.fs.child(ROOT, "b").unwrap();
+		assert_eq!(shell.fs.nodes[b].kind, NodeKind::File(10));
+		assert_eq!(shell.fs.sizes()[ROOT], 10);
+		assert_eq!(shell.cwd, shell.fs.child(ROOT, "a").unwrap());
+	}
+
+	#[test]
+	fn test_queries() {
+		let fs = parse_transcript(INPUT);
+
+		assert_eq!(
+			query(&fs, "tree"),
+			Ok(String::from(concat!(
+				"- / (dir, size=48381165)\n",
+				"  - a (dir, size=94853)\n",
+				"    - e (dir, size=584)\n",
+				"      - i (file, size=584)\n",
+				"    - f (file, size=29116)\n",
+				"    - g (file, size=2557)\n",
+				"    - h.lst (file, size=62596)\n",
+				"  - b.txt (file, size=14848514)\n",
+				"  - c.dat (file, size=8504156)\n",
+				"  - d (dir, size=24933642)\n",
+				"    - j (file, size=4060174)\n",
+				"    - d.log (file, size=8033020)\n",
+				"    - d.ext (file, size=5626152)\n",
+				"    - k (file, size=7214296)\n",
+			)))
+		);
+
+		assert_eq!(
+			query(&fs, "du -d 1"),
+			Ok(String::from("93K\t/a\n24M\t/d\n47M\t/\n"))
+		);
+		assert_eq!(query(&fs, "du"), Ok(String::from("47M\t/\n")));
+		assert_eq!(
+			query(&fs, "du -d 2"),
+			Ok(String::from("584\t/a/e\n93K\t/a\n24M\t/d\n47M\t/\n"))
+		);
+
+		assert_eq!(
+			query(&fs, "find -name *.???"),
+			Ok(String::from(
+				"/a/h.lst\n/b.txt\n/c.dat\n/d/d.log\n/d/d.ext\n"
+			))
+		);
+		assert_eq!(
+			query(&fs, "find -name d* -size +8000000"),
+			Ok(String::from("/d\n/d/d.log\n"))
+		);
+		assert_eq!(
+			query(&fs, "find -size -3000"),
+			Ok(String::from("/a/e\n/a/e/i\n/a/g\n"))
+		);
+
+		assert_eq!(
+			query(&fs, "largest 3"),
+			Ok(String::from("15M\t/b.txt\n8.2M\t/c.dat\n7.7M\t/d/d.log\n"))
+		);
+
+		assert!(query(&fs, "largest many").is_err());
+		assert!(query(&fs, "rm -rf /").is_err());
+	}
+
+	#[test]
+	fn test_human_size() {
+		assert_eq!(human_size(1023), "1023");
+		assert_eq!(human_size(1024), "1.0K");
+		assert_eq!(human_size(1025), "1.1K");
+		assert_eq!(human_size(10239), "10K");
+		assert_eq!(human_size(10240), "10K");
+		assert_eq!(human_size(10241), "11K");
+		assert_eq!(human_size(1048575), "1.0M");
+		assert_eq!(human_size(1048576), "1.0M");
+		assert_eq!(human_size(8504156), "8.2M");
+	}
+
+	#[test]
+	fn test_glob_matches() {
+		assert!(glob_matches("*", ""));
+		assert!(glob_matches("*.txt", "b.txt"));
+		assert!(!glob_matches("*.txt", "b.txt.bak"));
+		assert!(glob_matches("a*b*c", "aXXbYYbc"));
+		assert!(glob_matches("?.dat", "c.dat"));
+		assert!(!glob_matches("?.dat", "cc.dat"));
+	}
+
+	#[test]
+	fn test_plan_deletion() {
+		let fs = parse_transcript(INPUT);
+		let names =
+			|plan: &DeletionPlan| plan.nodes.iter().map(|id| fs.path(*id)).collect::<Vec<_>>();
+
+		let plan = plan_deletion(&fs, &DeletionOptions::default()).unwrap();
+		assert_eq!(names(&plan), vec!["/d"]);
+		assert_eq!(plan.freed, 24933642);
+
+		let roomy = DeletionOptions {
+			capacity: 100000000,
+			..DeletionOptions::default()
+		};
+		let plan = plan_deletion(&fs, &roomy).unwrap();
+		assert_eq!(plan.nodes, vec![]);
+
+		let protect_d = DeletionOptions {
+			protected: vec![String::from("/d")],
+			..DeletionOptions::default()
+		};
+		assert_eq!(plan_deletion(&fs, &protect_d), None);
+
+		let files = DeletionOptions {
+			include_files: true,
+			protected: vec![String::from("/d")],
+			..DeletionOptions::default()
+		};
+		let plan = plan_deletion(&fs, &files).unwrap();
+		assert_eq!(names(&plan), vec!["/c.dat"]);
+
+		let two_files = DeletionOptions {
+			required: 30168835,
+			include_dirs: false,
+			include_files: true,
+			protected: vec![String::from("/d"), String::from("/b.txt")],
+			..DeletionOptions::default()
+		};
+		let plan = plan_deletion(&fs, &two_files).unwrap();
+		assert_eq!(names(&plan), vec!["/c.dat", "/a/h.lst"]);
+		assert_eq!(plan.freed, 8566752);
+
+		let keep_i = DeletionOptions {
+			required: 30168835,
+			include_files: true,
+			protected: vec![String::from("/a/e/i")],
+			..DeletionOptions::default()
+		};
+		let plan = plan_deletion(&fs, &keep_i).unwrap();
+		assert_eq!(names(&plan), vec!["/b.txt"]);
+		assert!(plan.exact);
+
+		// two picks only get as far as the biggest directory
+		let cut_short = DeletionOptions {
+			max_picks: Some(2),
+			..keep_i
+		};
+		let plan = plan_deletion(&fs, &cut_short).unwrap();
+		assert_eq!(names(&plan), vec!["/d"]);
+		assert!(!plan.exact);
+	}
+
+	#[test]
+	fn test_transcript() {
+		let fs = parse_transcript(INPUT);
+		let options = TranscriptOptions {
+			order: Traversal::DepthFirst,
+			relist: false,
+		};
+		assert_eq!(transcript(&fs, &options), INPUT);
+
+		let options = TranscriptOptions {
+			order: Traversal::BreadthFirst,
+			relist: true,
+		};
+		let generated = transcript(&fs, &options);
+		assert_eq!(generated.lines().filter(|l| *l == "$ ls").count(), 8);
+		assert!(generated
+			.contains("$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d\n$ cd a\n$ ls\n"));
+
+		let replayed = Shell::replay(&generated);
+		assert!(replayed.warnings.is_empty());
+		assert_eq!(replayed.fs.tree(), fs.tree());
+	}
+
+	#[test]
+	fn test_from_dir() {
+		let root = std::env::temp_dir().join(format!("day07-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(root.join("a/e")).unwrap();
+		fs::create_dir_all(root.join("d")).unwrap();
+		fs::write(root.join("b.txt"), vec![0; 1500]).unwrap();
+		fs::write(root.join("a/f"), vec![0; 300]).unwrap();
+		fs::write(root.join("a/e/i"), vec![0; 20]).unwrap();
+		fs::write(root.join("d/j"), vec![0; 4000]).unwrap();
+		fs::write(root.join("d/not listed"), vec![0; 1]).unwrap();
+
+		let disk = FileSystem::from_dir(&root);
+		fs::remove_dir_all(&root).unwrap();
+		let (disk, skipped) = disk.unwrap();
+
+		assert_eq!(skipped, vec![root.join("d/not listed")]);
+
+		assert_eq!(
+			disk.tree(),
+			concat!(
+				"- / (dir, size=5820)\n",
+				"  - a (dir, size=320)\n",
+				"    - e (dir, size=20)\n",
+				"      - i (file, size=20)\n",
+				"    - f (file, size=300)\n",
+				"  - b.txt (file, size=1500)\n",
+				"  - d (dir, size=4000)\n",
+				"    - j (file, size=4000)\n",
+			)
+		);
+
+		for order in [Traversal::DepthFirst, Traversal::BreadthFirst] {
+			for relist in [false, true] {
+				let options = TranscriptOptions { order, relist };
+				let replayed = Shell::replay(&transcript(&disk, &options));
+				assert!(replayed.warnings.is_empty());
+				assert_eq!(replayed.fs.tree(), disk.tree());
+			}
+		}
+	}
+
 	#[test]
 	fn test_part02() {
 		let input = concat!(
@@ -1,7 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::env;
 use std::fs;
+use std::io;
+use std::io::{stdout, IsTerminal};
 use std::path::Path;
 
+extern crate test;
+
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
 	let input_path = format!("src/{module_name}/input.txt");
@@ -15,162 +20,431 @@ pub fn run() {
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
 	assert_eq!(part02_result, 671160);
+
+	let forest = Forest::parse(&input);
+	let visible = visible_from_outside(&forest);
+	let scores = scenic_scores(&forest);
+
+	// images are only written on request, e.g. `EXPORT_DIR=out cargo run day08`
+	if let Some(dir) = env::var_os("EXPORT_DIR") {
+		match export_images(Path::new(&dir), &forest, &visible, &scores) {
+			Ok(()) => println!("wrote images to {}", Path::new(&dir).display()),
+			Err(e) => println!("Could not write images: {}", e),
+		}
+	}
+
+	let sites = best_sites(&forest, &scores, 3, Some(10));
+	for site in sites {
+		let position = forest.coords(site);
+		let seen = visible_from(&forest, position, forest.heights[site] + 1);
+		println!(
+			"site {:?} scores {}, a treehouse on it sees {} trees",
+			position,
+			scores[site],
+			seen.len()
+		);
+	}
+
+	if stdout().is_terminal() {
+		print!("{}", ansi_heatmap(&forest, &visible, &scores));
+	}
 }
 
 fn part01(input: &str) -> usize {
-	let map = input
-		.lines()
-		.map(|l| {
-			l.chars()
-				.map(|c| c.to_digit(10).unwrap())
-				.collect::<Vec<_>>()
-		})
-		.collect::<Vec<_>>();
+	let forest = Forest::parse(input);
 
-	let size = map.len();
-	let map = map.concat();
+	visible_from_outside(&forest).len()
+}
+
+// trees taller than every tree between them and at least one edge
+fn visible_from_outside(forest: &Forest) -> HashSet<usize> {
 	let mut visible = HashSet::new();
 
-	// from top
-	let top_range = 0..size;
-	fn top_iterate(c: usize, s: usize) -> Option<usize> {
-		c.checked_add(s)
+	for line in forest.lines() {
+		let mut tallest = None;
+		for id in line {
+			let height = forest.heights[id];
+			if tallest.map_or(true, |t| height > t) {
+				tallest = Some(height);
+				visible.insert(id);
+			}
+		}
 	}
-	visible_from_outside(&map, &mut visible, size, top_range, top_iterate);
 
-	// from left
-	let left_range = (0..(size * size)).step_by(size);
-	fn left_iterate(c: usize, _s: usize) -> Option<usize> {
-		c.checked_add(1)
+	visible
+}
+
+// heights row by row in one flat list
+struct Forest {
+	width: usize,
+	height: usize,
+	heights: Vec<u8>,
+}
+
+impl Forest {
+	fn parse(input: &str) -> Self {
+		let width = input.lines().next().unwrap().len();
+		let heights = input
+			.lines()
+			.flat_map(|l| {
+				assert_eq!(l.len(), width, "Rows of different widths");
+				l.bytes().map(|b| b - b'0')
+			})
+			.collect::<Vec<_>>();
+		let height = heights.len() / width;
+
+		Forest {
+			width,
+			height,
+			heights,
+		}
 	}
-	visible_from_outside(&map, &mut visible, size, left_range, left_iterate);
 
-	// from right
-	let right_range = ((size - 1)..(size * size)).step_by(size);
-	fn right_iterate(c: usize, _s: usize) -> Option<usize> {
-		c.checked_sub(1)
+	fn coords(&self, id: usize) -> (usize, usize) {
+		(id % self.width, id / self.width)
 	}
-	visible_from_outside(&map, &mut visible, size, right_range, right_iterate);
 
-	// from bottom
-	let bottom_range = (size * size - size)..(size * size);
-	fn bottom_iterate(c: usize, s: usize) -> Option<usize> {
-		c.checked_sub(s)
+	// every row and column, walked in both directions
+	fn lines(&self) -> impl Iterator<Item = Vec<usize>> + '_ {
+		let (width, height) = (self.width, self.height);
+		let rows = (0..height).map(move |y| (0..width).map(|x| y * width + x).collect::<Vec<_>>());
+		let cols = (0..width).map(move |x| (0..height).map(|y| y * width + x).collect::<Vec<_>>());
+
+		rows.chain(cols).flat_map(|line| {
+			let reversed = line.iter().rev().copied().collect::<Vec<_>>();
+			[line, reversed]
+		})
 	}
-	visible_from_outside(&map, &mut visible, size, bottom_range, bottom_iterate);
+}
 
-	visible.len()
+fn part02(input: &str) -> usize {
+	let forest = Forest::parse(input);
+
+	scenic_scores(&forest).into_iter().max().unwrap()
 }
 
-fn visible_from_outside<I>(
-	map: &Vec<u32>,
-	visible: &mut HashSet<(usize, u32)>,
-	size: usize,
-	range: I,
-	iterate: fn(usize, usize) -> Option<usize>,
-) where
-	I: Iterator<Item = usize>,
-{
-	for i in range {
-		let mut curr_height = None;
-		let mut cursor = i;
+fn scenic_scores(forest: &Forest) -> Vec<usize> {
+	let mut scores = vec![1; forest.heights.len()];
+	let mut stack = vec![];
 
-		while let Some(tree_compared) = map.get(cursor) {
-			if let Some(height) = curr_height {
-				if *tree_compared > height {
-					curr_height = Some(*tree_compared);
-					visible.insert((cursor, *tree_compared));
-				}
-			} else {
-				curr_height = Some(*tree_compared);
-				visible.insert((cursor, *tree_compared));
-			}
+	for line in forest.lines() {
+		look_back(&forest.heights, &line, &mut scores, &mut stack);
+	}
 
-			if let Some(new_cursor) = iterate(cursor, size) {
-				cursor = new_cursor;
-			} else {
+	scores
+}
+
+// multiplies into `scores` how many trees each tree on `line` sees looking back
+// towards the start of the line; the stack keeps the positions of trees not yet
+// blocked by a later one, so their heights only ever decrease
+fn look_back(heights: &[u8], line: &[usize], scores: &mut [usize], stack: &mut Vec<usize>) {
+	stack.clear();
+
+	for (pos, id) in line.iter().enumerate() {
+		let height = heights[*id];
+		while let Some(top) = stack.last() {
+			if heights[line[*top]] >= height {
 				break;
 			}
+			stack.pop();
 		}
+
+		let distance = match stack.last() {
+			Some(blocker) => pos - blocker,
+			None => pos,
+		};
+		scores[*id] *= distance;
+		stack.push(pos);
 	}
 }
 
-type Map = HashMap<(usize, usize), u32>;
+// the four edge directions first, then the diagonals
+const DIRECTIONS: [(isize, isize); 8] = [
+	(0, -1),
+	(1, 0),
+	(0, 1),
+	(-1, 0),
+	(1, -1),
+	(1, 1),
+	(-1, 1),
+	(-1, -1),
+];
+
+// trees seen from (x, y) with eyes at `height` walking along `step`: everything
+// up to and including the first tree at least that tall
+fn sight_line(
+	forest: &Forest,
+	(x, y): (usize, usize),
+	height: u8,
+	step: (isize, isize),
+) -> Vec<usize> {
+	let mut seen = vec![];
+	let (mut cx, mut cy) = (x as isize + step.0, y as isize + step.1);
+
+	while (0..forest.width as isize).contains(&cx) && (0..forest.height as isize).contains(&cy) {
+		let id = cy as usize * forest.width + cx as usize;
+		seen.push(id);
+		if forest.heights[id] >= height {
+			break;
+		}
+		cx += step.0;
+		cy += step.1;
+	}
+
+	seen
+}
 
-fn part02(input: &str) -> usize {
-	let mut map: Map = HashMap::new();
+fn visible_from(forest: &Forest, position: (usize, usize), height: u8) -> Vec<usize> {
+	DIRECTIONS
+		.iter()
+		.flat_map(|step| sight_line(forest, position, height, *step))
+		.collect()
+}
 
-	let size = input.lines().next().unwrap().len();
+// the `k` best scoring trees, skipping any closer than `min_distance` steps
+// (up, down, left or right) to a better one already picked
+fn best_sites(
+	forest: &Forest,
+	scores: &[usize],
+	k: usize,
+	min_distance: Option<usize>,
+) -> Vec<usize> {
+	let mut ranked = (0..scores.len()).collect::<Vec<_>>();
+	ranked.sort_by(|a, b| scores[*b].cmp(&scores[*a]).then(a.cmp(b)));
+
+	let mut sites: Vec<usize> = vec![];
+	for id in ranked {
+		if sites.len() == k {
+			break;
+		}
 
-	input.lines().enumerate().for_each(|(y, cs)| {
-		cs.chars().enumerate().for_each(|(x, c)| {
-			let h = c.to_digit(10).unwrap();
-			map.insert((x, y), h);
+		let (x, y) = forest.coords(id);
+		let far_enough = sites.iter().all(|site| {
+			let (sx, sy) = forest.coords(*site);
+			min_distance.map_or(true, |min| x.abs_diff(sx) + y.abs_diff(sy) >= min)
 		});
-	});
+		if far_enough {
+			sites.push(id);
+		}
+	}
 
-	let mut scenic_scores = map
-		.iter()
-		.map(|(coord, _height)| {
-			// to top
-			let top_list = (0..=coord.1)
-				.rev()
-				.map(|y| map.get_key_value(&(coord.0, y)).unwrap())
-				.collect::<Vec<_>>();
+	sites
+}
 
-			let visible_top = visible_in_list(&top_list);
+// visible trees in green and hidden ones in grey, brighter the taller they are
+fn visibility_colors(forest: &Forest, visible: &HashSet<usize>) -> Vec<[u8; 3]> {
+	(0..forest.heights.len())
+		.map(|id| {
+			let height = forest.heights[id];
+			if visible.contains(&id) {
+				[0, 80 + height * 19, 0]
+			} else {
+				let grey = 30 + height * 10;
+				[grey, grey, grey]
+			}
+		})
+		.collect()
+}
 
-			// to right
-			let right_list = (coord.0..size)
-				.map(|x| map.get_key_value(&(x, coord.1)).unwrap())
-				.collect::<Vec<_>>();
+// scores scaled to 0..=255 against the best one
+fn scenic_shades(scores: &[usize]) -> Vec<u8> {
+	let best = scores.iter().max().copied().unwrap_or(0).max(1);
 
-			let visible_right = visible_in_list(&right_list);
+	scores.iter().map(|s| (s * 255 / best) as u8).collect()
+}
 
-			// to left
-			let left_list = (0..=coord.0)
-				.rev()
-				.map(|x| map.get_key_value(&(x, coord.1)).unwrap())
-				.collect::<Vec<_>>();
+// black through red and yellow to white
+fn heat(shade: u8) -> [u8; 3] {
+	let t = shade as usize * 3;
+	[
+		t.min(255),
+		t.saturating_sub(255).min(255),
+		t.saturating_sub(510),
+	]
+	.map(|c| c as u8)
+}
 
-			let visible_left = visible_in_list(&left_list);
+fn pgm(forest: &Forest, shades: &[u8]) -> Vec<u8> {
+	let mut image = format!("P5\n{} {}\n255\n", forest.width, forest.height).into_bytes();
+	image.extend(shades);
+	image
+}
 
-			// to bottom
-			let bottom_list = (coord.1..size)
-				.map(|y| map.get_key_value(&(coord.0, y)).unwrap())
-				.collect::<Vec<_>>();
+fn ppm(forest: &Forest, colors: &[[u8; 3]]) -> Vec<u8> {
+	let mut image = format!("P6\n{} {}\n255\n", forest.width, forest.height).into_bytes();
+	image.extend(colors.iter().flatten());
+	image
+}
 
-			let visible_bottom = visible_in_list(&bottom_list);
+fn export_images(
+	dir: &Path,
+	forest: &Forest,
+	visible: &HashSet<usize>,
+	scores: &[usize],
+) -> io::Result<()> {
+	fs::create_dir_all(dir)?;
+	let visibility = ppm(forest, &visibility_colors(forest, visible));
+	fs::write(dir.join("day08-visibility.ppm"), visibility)?;
+	let scenic = pgm(forest, &scenic_shades(scores));
+	fs::write(dir.join("day08-scenic.pgm"), scenic)
+}
 
-			visible_top.len() * visible_left.len() * visible_right.len() * visible_bottom.len()
+// every tree as its height digit, coloured by visibility on a background
+// heated by its scenic score
+fn ansi_heatmap(forest: &Forest, visible: &HashSet<usize>, scores: &[usize]) -> String {
+	let colors = visibility_colors(forest, visible);
+	let shades = scenic_shades(scores);
+
+	forest
+		.heights
+		.chunks(forest.width)
+		.enumerate()
+		.map(|(y, row)| {
+			let trees = row
+				.iter()
+				.enumerate()
+				.map(|(x, height)| {
+					let id = y * forest.width + x;
+					let [r, g, b] = colors[id];
+					let [br, bg, bb] = heat(shades[id]);
+					format!("\x1b[38;2;{r};{g};{b}m\x1b[48;2;{br};{bg};{bb}m{height}")
+				})
+				.collect::<String>();
+			trees + "\x1b[0m\n"
 		})
-		.collect::<Vec<_>>();
-
-	scenic_scores.sort();
-	let best_scenic_score = scenic_scores.last().unwrap();
-	*best_scenic_score
+		.collect()
 }
 
-fn visible_in_list(list: &Vec<(&(usize, usize), &u32)>) -> Vec<(usize, usize)> {
-	let mut visible = vec![];
-	let mut iterator = list.iter();
-	let (_, house_height) = iterator.next().unwrap();
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_input::numbers;
+	use test::Bencher;
+
+	fn generate_forest(width: usize, height: usize) -> String {
+		let mut next = numbers(8);
+		(0..height)
+			.map(|_| {
+				let row = (0..width)
+					.map(|_| (b'0' + (next() % 10) as u8) as char)
+					.collect::<String>();
+				row + "\n"
+			})
+			.collect()
+	}
 
-	for (coord, tree_height) in iterator {
-		if tree_height >= house_height {
-			visible.push(**coord);
-			break;
-		} else {
-			visible.push(**coord);
+	// walks outwards from every tree, one step at a time
+	fn naive_scenic_scores(forest: &Forest) -> Vec<usize> {
+		let (width, height) = (forest.width as isize, forest.height as isize);
+		let at = |x: isize, y: isize| forest.heights[(y * width + x) as usize];
+
+		(0..forest.heights.len() as isize)
+			.map(|id| {
+				let (x, y) = (id % width, id / width);
+				[(0, -1), (0, 1), (-1, 0), (1, 0)]
+					.iter()
+					.map(|(dx, dy)| {
+						let (mut cx, mut cy, mut seen) = (x + dx, y + dy, 0);
+						while (0..width).contains(&cx) && (0..height).contains(&cy) {
+							seen += 1;
+							if at(cx, cy) >= at(x, y) {
+								break;
+							}
+							cx += dx;
+							cy += dy;
+						}
+						seen
+					})
+					.product()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_scenic_scores() {
+		let input = concat!("30373\n", "25512\n", "65332\n", "33549\n", "35390\n",);
+		let forest = Forest::parse(input);
+		let scores = scenic_scores(&forest);
+		assert_eq!(scores[7], 4);
+		assert_eq!(scores[17], 8);
+		assert_eq!(scores, naive_scenic_scores(&forest));
+
+		for (width, height) in [(40, 40), (7, 60), (60, 7), (1, 9), (9, 1)] {
+			let forest = Forest::parse(&generate_forest(width, height));
+			assert_eq!(scenic_scores(&forest), naive_scenic_scores(&forest));
 		}
 	}
 
-	visible
-}
+	#[test]
+	fn test_images() {
+		let input = concat!("30373\n", "25512\n", "65332\n", "33549\n", "35390\n",);
+		let forest = Forest::parse(input);
+		let visible = visible_from_outside(&forest);
+		let scores = scenic_scores(&forest);
+
+		let shades = scenic_shades(&scores);
+		assert_eq!(shades[17], 255);
+		assert_eq!(shades[7], 127);
+		assert_eq!(shades.iter().filter(|s| **s == 0).count(), 16);
+
+		let image = pgm(&forest, &shades);
+		assert!(image.starts_with(b"P5\n5 5\n255\n"));
+		assert_eq!(image.len(), 11 + 25);
+
+		let colors = visibility_colors(&forest, &visible);
+		assert_eq!(colors[0], [0, 137, 0]);
+		assert_eq!(colors[12], [60, 60, 60]);
+		let image = ppm(&forest, &colors);
+		assert!(image.starts_with(b"P6\n5 5\n255\n"));
+		assert_eq!(image.len(), 11 + 25 * 3);
+
+		assert_eq!(heat(0), [0, 0, 0]);
+		assert_eq!(heat(100), [255, 45, 0]);
+		assert_eq!(heat(255), [255, 255, 255]);
+
+		let rendered = ansi_heatmap(&forest, &visible, &scores);
+		assert_eq!(rendered.lines().count(), 5);
+		assert!(rendered.starts_with("\x1b[38;2;0;137;0m\x1b[48;2;0;0;0m3"));
+		assert!(rendered.contains("\x1b[38;2;0;175;0m\x1b[48;2;255;255;255m5"));
+	}
 
-#[cfg(test)]
-mod tests {
-	use super::*;
+	#[test]
+	fn test_line_of_sight() {
+		let input = concat!("30373\n", "25512\n", "65332\n", "33549\n", "35390\n",);
+		let forest = Forest::parse(input);
+
+		let lines = DIRECTIONS
+			.iter()
+			.map(|step| sight_line(&forest, (2, 3), 5, *step).len())
+			.collect::<Vec<_>>();
+		assert_eq!(lines, vec![2, 2, 1, 2, 2, 1, 1, 1]);
+		assert_eq!(sight_line(&forest, (2, 3), 5, (-1, 0)), vec![16, 15]);
+		assert_eq!(sight_line(&forest, (2, 3), 5, (1, -1)), vec![13, 9]);
+
+		assert_eq!(visible_from(&forest, (2, 3), 10).len(), 14);
+		assert_eq!(visible_from(&forest, (0, 0), 0), vec![1, 5, 6]);
+		assert!(visible_from(&Forest::parse("7\n"), (0, 0), 9).is_empty());
+
+		let scores = scenic_scores(&forest);
+		assert_eq!(best_sites(&forest, &scores, 3, None), vec![17, 11, 7]);
+		assert_eq!(best_sites(&forest, &scores, 2, Some(3)), vec![17, 6]);
+		assert_eq!(best_sites(&forest, &scores, 0, None), vec![]);
+	}
+
+	#[test]
+	fn test_rectangular() {
+		let wide = concat!("25512\n", "65332\n", "33549\n");
+		let forest = Forest::parse(wide);
+		assert_eq!((forest.width, forest.height), (5, 3));
+		assert_eq!(part01(wide), 14);
+		assert_eq!(part02(wide), 3);
+
+		let tall = concat!("373\n", "512\n", "332\n", "549\n", "390\n", "210\n", "456\n");
+		let forest = Forest::parse(tall);
+		assert_eq!((forest.width, forest.height), (3, 7));
+		assert_eq!(part01(tall), 19);
+		assert_eq!(part02(tall), 8);
+	}
 
 	#[test]
 	fn test_part01() {
@@ -185,4 +459,10 @@ mod tests {
 		let result = part02(input);
 		assert_eq!(result, 8);
 	}
+
+	#[bench]
+	fn bench_scenic_scores(b: &mut Bencher) {
+		let forest = Forest::parse(&generate_forest(1000, 1000));
+		b.iter(|| scenic_scores(&forest));
+	}
 }
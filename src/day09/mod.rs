@@ -1,6 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
+use std::io;
+use std::io::{stdout, IsTerminal, Write};
 use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
 
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
@@ -15,6 +20,57 @@ pub fn run() {
 	let part02_result = part02(&input);
 	println!("part02 {:?}", part02_result);
 	assert_eq!(part02_result, 2604);
+
+	let config = RopeConfig {
+		knots: 10,
+		..RopeConfig::default()
+	};
+	let visited = simulate(&input, &config);
+	let (busiest, cells) = visited
+		.iter()
+		.enumerate()
+		.skip(1)
+		.max_by_key(|(i, v)| (v.len(), usize::MAX - i))
+		.unwrap();
+	println!(
+		"following knot {} visits the most cells: {}",
+		busiest,
+		cells.len()
+	);
+
+	let config = RopeConfig {
+		knots: 10,
+		slack: 2,
+	};
+	let tail = simulate(&input, &config).last().unwrap().len();
+	println!("tail with slack 2 visits {}", tail);
+
+	let example = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20\n";
+	let config = RopeConfig {
+		knots: 10,
+		..RopeConfig::default()
+	};
+	let frames = record(example, &config);
+
+	// frames and the trail are saved on request, e.g. `EXPORT_DIR=out cargo run day09`,
+	// and played in the terminal otherwise
+	if let Some(dir) = env::var_os("EXPORT_DIR") {
+		let dir = Path::new(&dir);
+		let trail = render_trail(visited.last().unwrap());
+		let written = fs::create_dir_all(dir)
+			.and_then(|_| fs::write(dir.join("day09-trail.txt"), trail))
+			.and_then(|_| write_frames(&frames, &dir.join("day09-frames")));
+		match written {
+			Ok(()) => println!(
+				"wrote the trail and {} frames to {}",
+				frames.len(),
+				dir.display()
+			),
+			Err(e) => println!("Could not write frames: {}", e),
+		}
+	} else if stdout().is_terminal() {
+		play(&frames, Duration::from_secs_f64(1f64 / 30f64));
+	}
 }
 
 #[derive(Debug, PartialEq, Hash, Eq, Default, Clone)]
@@ -23,12 +79,63 @@ struct Coord {
 	y: isize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum Move {
 	U(isize),
 	D(isize),
 	L(isize),
 	R(isize),
+	UL(isize),
+	UR(isize),
+	DL(isize),
+	DR(isize),
+}
+
+impl Move {
+	fn parse(line: &str) -> Self {
+		let (i, s) = line.split_once(' ').unwrap();
+		let size = s.parse().unwrap();
+
+		match i {
+			"U" => Move::U(size),
+			"D" => Move::D(size),
+			"L" => Move::L(size),
+			"R" => Move::R(size),
+			"UL" => Move::UL(size),
+			"UR" => Move::UR(size),
+			"DL" => Move::DL(size),
+			"DR" => Move::DR(size),
+			_ => panic!("Unexpected move {}", i),
+		}
+	}
+
+	// how far the head goes and which way it goes each step
+	fn steps(&self) -> (isize, Coord) {
+		let (size, x, y) = match *self {
+			Move::U(s) => (s, 0, 1),
+			Move::D(s) => (s, 0, -1),
+			Move::L(s) => (s, -1, 0),
+			Move::R(s) => (s, 1, 0),
+			Move::UL(s) => (s, -1, 1),
+			Move::UR(s) => (s, 1, 1),
+			Move::DL(s) => (s, -1, -1),
+			Move::DR(s) => (s, 1, -1),
+		};
+
+		(size, Coord { x, y })
+	}
+}
+
+struct RopeConfig {
+	knots: usize,
+	// how far apart two knots may get before the one behind is pulled along
+	slack: usize,
+}
+
+impl Default for RopeConfig {
+	fn default() -> Self {
+		RopeConfig { knots: 2, slack: 1 }
+	}
 }
 
 fn part01(input: &str) -> usize {
@@ -39,116 +146,525 @@ fn part02(input: &str) -> usize {
 	simulate_rope(input, 10)
 }
 
-fn is_far(head: &Coord, tail: &Coord) -> bool {
+fn is_far(head: &Coord, tail: &Coord, slack: usize) -> bool {
 	let x_diff = head.x - tail.x;
 	let y_diff = head.y - tail.y;
-	x_diff.abs() > 1 || y_diff.abs() > 1
+	x_diff.unsigned_abs() > slack || y_diff.unsigned_abs() > slack
 }
 
 fn simulate_rope(input: &str, rope_size: usize) -> usize {
-	let start = Coord::default();
-	let mut rope = (0..rope_size).map(|_| start.clone()).collect::<Vec<_>>();
-	let mut tail_visited: HashSet<Coord> = HashSet::new();
-	tail_visited.insert(start.clone());
-	let instructions = input.lines().flat_map(|l| {
-		let (i, s) = l.split_once(' ').unwrap();
-		let size = s.parse().unwrap();
+	let config = RopeConfig {
+		knots: rope_size,
+		..RopeConfig::default()
+	};
+
+	count_visited(input, &config).last().copied().unwrap()
+}
+
+// moves with more steps left than this once the rope is straight are finished in one go
+const FAST_FORWARD_AFTER: isize = 32;
+
+// what a run through the moves keeps of the rope
+trait Recorder {
+	// the rope before the first step and after every single one
+	fn record(&mut self, motion: &str, rope: &[Coord]);
+
+	// the rest of a long move once it has pulled the rope straight, taken all at once
+	// if the recorder doesn't need every step of it
+	fn skip(&mut self, _rope: &[Coord], _direction: &Coord, _steps: isize) -> bool {
+		false
+	}
+}
+
+fn drive(input: &str, config: &RopeConfig, recorder: &mut impl Recorder) {
+	assert!(config.knots > 0, "A rope needs at least one knot");
+	let mut rope = vec![Coord::default(); config.knots];
+	recorder.record("Initial State", &rope);
+
+	for line in input.lines() {
+		let (mut size, direction) = Move::parse(line).steps();
+
+		while size > 0 {
+			let before = (size > FAST_FORWARD_AFTER).then(|| rope.clone());
+			step_rope(&mut rope, &direction, config.slack);
+			size -= 1;
+			recorder.record(line, &rope);
+
+			// every knot moving like the head leaves the rope as it was, so each
+			// step after this one does the same
+			let straight = before.is_some_and(|before| {
+				rope.iter()
+					.zip(before.iter())
+					.all(|(a, b)| a.x - b.x == direction.x && a.y - b.y == direction.y)
+			});
+			if straight && size > FAST_FORWARD_AFTER && recorder.skip(&rope, &direction, size) {
+				for knot in rope.iter_mut() {
+					knot.x += direction.x * size;
+					knot.y += direction.y * size;
+				}
+				size = 0;
+			}
+		}
+	}
+}
 
-		(0..size).map(move |_| match i {
-			"U" => Move::U(1),
-			"D" => Move::D(1),
-			"L" => Move::L(1),
-			"R" => Move::R(1),
-			_ => panic!("Unexpected char"),
+impl Recorder for Vec<HashSet<Coord>> {
+	fn record(&mut self, _motion: &str, rope: &[Coord]) {
+		self.resize_with(rope.len(), HashSet::new);
+		for (knot, cells) in rope.iter().zip(self.iter_mut()) {
+			cells.insert(knot.clone());
+		}
+	}
+}
+
+impl Recorder for Vec<Trail> {
+	fn record(&mut self, _motion: &str, rope: &[Coord]) {
+		self.resize_with(rope.len(), Trail::default);
+		for (knot, trail) in rope.iter().zip(self.iter_mut()) {
+			trail.insert(knot.clone());
+		}
+	}
+
+	fn skip(&mut self, rope: &[Coord], direction: &Coord, steps: isize) -> bool {
+		for (knot, trail) in rope.iter().zip(self.iter_mut()) {
+			trail.extend(knot, direction, steps);
+		}
+		true
+	}
+}
+
+impl Recorder for Vec<Frame> {
+	fn record(&mut self, motion: &str, rope: &[Coord]) {
+		self.push(Frame {
+			motion: String::from(motion),
+			knots: rope.to_vec(),
+		});
+	}
+}
+
+// how many cells every knot visits, like `simulate`, without stepping through
+// the rest of a long move once it has pulled the rope straight
+fn count_visited(input: &str, config: &RopeConfig) -> Vec<usize> {
+	let mut trails: Vec<Trail> = vec![];
+	drive(input, config, &mut trails);
+
+	trails.iter().map(Trail::len).collect()
+}
+
+// single cells, plus straight runs that would be too many cells to keep one by one
+#[derive(Default, Clone)]
+struct Trail {
+	cells: HashSet<Coord>,
+	// the line a run is on and the stretch of it that was covered, see `Line`
+	runs: Vec<(Line, isize, isize)>,
+}
+
+// cells with the same `key` along one of the four orientations, told apart by `x`
+// except on vertical lines, where it's `y`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+struct Line {
+	orientation: (isize, isize),
+	key: isize,
+}
+
+impl Line {
+	const ORIENTATIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+	// the normal of the orientation, whose dot product with a cell is the key
+	fn normal(orientation: (isize, isize)) -> (isize, isize) {
+		(-orientation.1, orientation.0)
+	}
+
+	fn through(c: &Coord, orientation: (isize, isize)) -> (Line, isize) {
+		let (nx, ny) = Line::normal(orientation);
+		let line = Line {
+			orientation,
+			key: nx * c.x + ny * c.y,
+		};
+		let t = if orientation == (0, 1) { c.y } else { c.x };
+		(line, t)
+	}
+
+	// the cell both lines go through, if there is a whole one
+	fn crossing(&self, other: &Line) -> Option<Coord> {
+		let (ax, ay) = Line::normal(self.orientation);
+		let (bx, by) = Line::normal(other.orientation);
+		let det = ax * by - ay * bx;
+		if det == 0 {
+			return None;
+		}
+
+		let x = self.key * by - ay * other.key;
+		let y = ax * other.key - self.key * bx;
+		if x % det != 0 || y % det != 0 {
+			return None;
+		}
+		Some(Coord {
+			x: x / det,
+			y: y / det,
 		})
-	});
-	instructions.for_each(|i| {
-		let head = rope.get_mut(0).unwrap();
+	}
+}
 
-		match i {
-			Move::U(s) => head.y += s,
-			Move::D(s) => head.y -= s,
-			Move::L(s) => head.x -= s,
-			Move::R(s) => head.x += s,
+impl Trail {
+	fn insert(&mut self, c: Coord) {
+		self.cells.insert(c);
+	}
+
+	// the `steps` cells after `from` going `direction`
+	fn extend(&mut self, from: &Coord, direction: &Coord, steps: isize) {
+		let to = Coord {
+			x: from.x + direction.x * steps,
+			y: from.y + direction.y * steps,
+		};
+		let first = Coord {
+			x: from.x + direction.x,
+			y: from.y + direction.y,
+		};
+		let orientation = if direction.x < 0 || direction.x == 0 && direction.y < 0 {
+			(-direction.x, -direction.y)
+		} else {
+			(direction.x, direction.y)
+		};
+
+		let (line, a) = Line::through(&first, orientation);
+		let (_, b) = Line::through(&to, orientation);
+		self.runs.push((line, a.min(b), a.max(b)));
+	}
+
+	fn len(&self) -> usize {
+		// overlapping runs on the same line merged into one
+		let mut runs = self.runs.clone();
+		runs.sort();
+		let mut merged: Vec<(Line, isize, isize)> = vec![];
+		for (line, from, to) in runs {
+			match merged.last_mut() {
+				Some((l, _, end)) if *l == line && from <= *end + 1 => *end = (*end).max(to),
+				_ => merged.push((line, from, to)),
+			}
 		}
 
-		for hi in 0..(rope_size - 1) {
-			let ti = hi + 1;
-			let [head, tail] = rope.get_many_mut([hi, ti]).unwrap();
-
-			while is_far(&head, &tail) {
-				if tail.x != head.x && tail.y != head.y {
-					// diagonal
-					if tail.x < head.x {
-						tail.x += 1;
-					} else {
-						tail.x -= 1;
-					}
-
-					if tail.y < head.y {
-						tail.y += 1;
-					} else {
-						tail.y -= 1;
-					}
-				} else if tail.x != head.x {
-					// x-axis
-					if tail.x < head.x {
-						tail.x += 1;
-					} else {
-						tail.x -= 1;
-					}
-				} else if tail.y != head.y {
-					// y-axis
-					if tail.y < head.y {
-						tail.y += 1;
-					} else {
-						tail.y -= 1;
-					}
-				}
+		let on_run = |c: &Coord| {
+			Line::ORIENTATIONS.iter().any(|o| {
+				let (line, t) = Line::through(c, *o);
+				let i = merged.partition_point(|r| (r.0, r.1) <= (line, t));
+				i > 0 && merged[i - 1].0 == line && merged[i - 1].2 >= t
+			})
+		};
 
-				// add visited for only tail tail
-				if ti == rope_size - 1 {
-					tail_visited.insert(tail.clone());
+		// cells where runs of different orientations cross, with the orientations
+		let mut crossings: HashMap<Coord, HashSet<(isize, isize)>> = HashMap::new();
+		for (i, (a, a_from, a_to)) in merged.iter().enumerate() {
+			for (b, b_from, b_to) in &merged[(i + 1)..] {
+				let Some(c) = a.crossing(b) else {
+					continue;
+				};
+				let (_, ta) = Line::through(&c, a.orientation);
+				let (_, tb) = Line::through(&c, b.orientation);
+				if (*a_from..=*a_to).contains(&ta) && (*b_from..=*b_to).contains(&tb) {
+					let orientations = crossings.entry(c).or_default();
+					orientations.insert(a.orientation);
+					orientations.insert(b.orientation);
 				}
 			}
 		}
-	});
-	tail_visited.len()
+
+		let run_cells = merged
+			.iter()
+			.map(|(_, from, to)| (to - from + 1) as usize)
+			.sum::<usize>();
+		let counted_twice = crossings.values().map(|o| o.len() - 1).sum::<usize>();
+		let loose_cells = self.cells.iter().filter(|c| !on_run(c)).count();
+
+		run_cells - counted_twice + loose_cells
+	}
+}
+
+// cells visited by every knot, the head first
+fn simulate(input: &str, config: &RopeConfig) -> Vec<HashSet<Coord>> {
+	let mut visited = vec![];
+	drive(input, config, &mut visited);
+
+	visited
+}
+
+// moves the head one step and pulls every other knot after it; a knot never ends
+// up more than one step past the slack, so one pull is always enough
+fn step_rope(rope: &mut [Coord], direction: &Coord, slack: usize) {
+	rope[0].x += direction.x;
+	rope[0].y += direction.y;
+
+	for ti in 1..rope.len() {
+		let [head, tail] = rope.get_many_mut([ti - 1, ti]).unwrap();
+
+		// diagonally while on neither axis of the head
+		if is_far(head, tail, slack) {
+			tail.x += (head.x - tail.x).signum();
+			tail.y += (head.y - tail.y).signum();
+		}
+	}
+}
+
+struct Frame {
+	motion: String,
+	knots: Vec<Coord>,
+}
+
+// the rope before the first step and after every single one
+fn record(input: &str, config: &RopeConfig) -> Vec<Frame> {
+	let mut frames = vec![];
+	drive(input, config, &mut frames);
+
+	frames
+}
+
+struct Bounds {
+	min: Coord,
+	max: Coord,
+}
+
+impl Bounds {
+	// the smallest area holding every coordinate and the start
+	fn around<'a>(coords: impl Iterator<Item = &'a Coord>) -> Self {
+		coords.fold(
+			Bounds {
+				min: Coord::default(),
+				max: Coord::default(),
+			},
+			|b, c| Bounds {
+				min: Coord {
+					x: b.min.x.min(c.x),
+					y: b.min.y.min(c.y),
+				},
+				max: Coord {
+					x: b.max.x.max(c.x),
+					y: b.max.y.max(c.y),
+				},
+			},
+		)
+	}
+
+	// rows from the top down, `cell` picking the character for each position
+	fn draw(&self, cell: impl Fn(&Coord) -> char) -> String {
+		(self.min.y..=self.max.y)
+			.rev()
+			.map(|y| {
+				let row = (self.min.x..=self.max.x)
+					.map(|x| cell(&Coord { x, y }))
+					.collect::<String>();
+				row + "\n"
+			})
+			.collect()
+	}
+}
+
+// `H` for the head, then `1` to `9` like the README; a two knot rope ends in `T`,
+// as do knots past the ninth
+fn knot_label(index: usize, knots: usize) -> char {
+	match index {
+		0 => 'H',
+		1 if knots == 2 => 'T',
+		1..=9 => char::from_digit(index as u32, 10).unwrap(),
+		_ => 'T',
+	}
+}
+
+// knots closer to the head are drawn over the ones behind them, the start
+// only shows when nothing covers it
+fn render_frame(frame: &Frame, bounds: &Bounds) -> String {
+	bounds.draw(|c| match frame.knots.iter().position(|k| k == c) {
+		Some(i) => knot_label(i, frame.knots.len()),
+		None if *c == Coord::default() => 's',
+		None => '.',
+	})
+}
+
+fn render_trail(visited: &HashSet<Coord>) -> String {
+	Bounds::around(visited.iter()).draw(|c| {
+		if *c == Coord::default() {
+			's'
+		} else if visited.contains(c) {
+			'#'
+		} else {
+			'.'
+		}
+	})
+}
+
+// every frame drawn on the same area, under the motion it belongs to
+fn render_frames(frames: &[Frame]) -> Vec<String> {
+	let bounds = Bounds::around(frames.iter().flat_map(|f| f.knots.iter()));
+
+	frames
+		.iter()
+		.map(|f| format!("== {} ==\n\n{}", f.motion, render_frame(f, &bounds)))
+		.collect()
+}
+
+fn play(frames: &[Frame], frame_time: Duration) {
+	for frame in render_frames(frames) {
+		print!("{esc}c{frame}", esc = 27 as char);
+		stdout().flush().unwrap();
+		sleep(frame_time);
+	}
+}
+
+fn write_frames(frames: &[Frame], dir: &Path) -> io::Result<()> {
+	fs::create_dir_all(dir)?;
+	for (i, frame) in render_frames(frames).iter().enumerate() {
+		fs::write(dir.join(format!("{:05}.txt", i)), frame)?;
+	}
+	Ok(())
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::test_input::numbers;
 
 	#[test]
 	fn test_is_far() {
 		let head = Coord { x: 3, y: 3 };
 		let tail = Coord { x: 0, y: 1 };
-		assert_eq!(true, is_far(&head, &tail));
+		assert_eq!(true, is_far(&head, &tail, 1));
 
 		let head = Coord { x: 1, y: 2 };
 		let tail = Coord { x: 1, y: 1 };
-		assert_eq!(false, is_far(&head, &tail));
+		assert_eq!(false, is_far(&head, &tail, 1));
 
 		let head = Coord { x: 2, y: 2 };
 		let tail = Coord { x: 1, y: 1 };
-		assert_eq!(false, is_far(&head, &tail));
+		assert_eq!(false, is_far(&head, &tail, 1));
 
 		let head = Coord { x: 0, y: 0 };
 		let tail = Coord { x: 1, y: -1 };
-		assert_eq!(false, is_far(&head, &tail));
+		assert_eq!(false, is_far(&head, &tail, 1));
 
 		let head = Coord { x: -1, y: 0 };
 		let tail = Coord { x: -1, y: -1 };
-		assert_eq!(false, is_far(&head, &tail));
+		assert_eq!(false, is_far(&head, &tail, 1));
 
 		let head = Coord { x: 2, y: 2 };
 		let tail = Coord { x: 2, y: 2 };
-		assert_eq!(false, is_far(&head, &tail));
+		assert_eq!(false, is_far(&head, &tail, 1));
 
 		let head = Coord { x: 0, y: 4 };
 		let tail = Coord { x: 0, y: 0 };
-		assert_eq!(true, is_far(&head, &tail));
+		assert_eq!(true, is_far(&head, &tail, 1));
+		assert_eq!(true, is_far(&head, &tail, 3));
+		assert_eq!(false, is_far(&head, &tail, 4));
+	}
+
+	fn visited_counts(input: &str, knots: usize, slack: usize) -> Vec<usize> {
+		let config = RopeConfig { knots, slack };
+		simulate(input, &config).iter().map(|v| v.len()).collect()
+	}
+
+	#[test]
+	fn test_simulate() {
+		let input =
+			concat!("R 5\n", "U 8\n", "L 8\n", "D 3\n", "R 17\n", "D 10\n", "L 25\n", "U 20\n",);
+		assert_eq!(
+			visited_counts(input, 10, 1),
+			vec![96, 88, 80, 72, 64, 56, 50, 46, 41, 36]
+		);
+		assert_eq!(
+			visited_counts(input, 10, 2),
+			vec![96, 79, 63, 50, 41, 31, 24, 18, 11, 6]
+		);
+
+		let input = concat!("UR 3\n", "DL 5\n", "UL 2\n", "R 3\n", "DR 4\n");
+		assert_eq!(Move::parse("UL 2"), Move::UL(2));
+		assert_eq!(visited_counts(input, 3, 1), vec![15, 10, 6]);
+		assert_eq!(visited_counts(input, 3, 2), vec![15, 6, 2]);
+		assert_eq!(visited_counts(input, 1, 1), vec![15]);
+	}
+
+	#[test]
+	#[should_panic(expected = "at least one knot")]
+	fn test_no_knots() {
+		visited_counts("R 1\n", 0, 1);
+	}
+
+	#[test]
+	fn test_count_visited() {
+		let mut next = numbers(9);
+		let names = ["U", "D", "L", "R", "UL", "UR", "DL", "DR"];
+		let input = (0..60)
+			.map(|_| format!("{} {}\n", names[next() % 8], 1 + next() % 120))
+			.collect::<String>();
+
+		// runs crossing each other in every orientation
+		let crossing = "R 100\nU 50\nL 50\nD 100\nUR 80\nDL 200\nUL 90\nR 300\n";
+
+		for input in [input.as_str(), crossing] {
+			for (knots, slack) in [(2, 1), (10, 1), (10, 2), (4, 3)] {
+				let config = RopeConfig { knots, slack };
+				let expected = simulate(input, &config)
+					.iter()
+					.map(|v| v.len())
+					.collect::<Vec<_>>();
+				assert_eq!(count_visited(input, &config), expected);
+			}
+		}
+
+		let input = "R 1000000000\nL 1000000000\n";
+		assert_eq!(part01(input), 1000000000);
+		assert_eq!(part02(input), 1000000000 - 8);
+
+		let config = RopeConfig::default();
+		let input = "R 1000\nUL 2000\nD 1000\n";
+		let counts = count_visited(input, &config);
+		assert_eq!(counts, vec![4000 + 1, 4000 - 3]);
+		let input = "R 1000000000\nUL 2000000000\nD 1000000000\n";
+		let counts = count_visited(input, &config);
+		assert_eq!(counts, vec![4000000000 + 1, 4000000000 - 3]);
+	}
+
+	#[test]
+	fn test_render() {
+		let input =
+			concat!("R 4\n", "U 4\n", "L 3\n", "D 1\n", "R 4\n", "D 1\n", "L 5\n", "R 2\n",);
+		let config = RopeConfig::default();
+		let frames = render_frames(&record(input, &config));
+		assert_eq!(frames.len(), 25);
+		assert_eq!(
+			frames[1],
+			"== R 4 ==\n\n......\n......\n......\n......\nTH....\n"
+		);
+		assert_eq!(
+			frames[4],
+			"== R 4 ==\n\n......\n......\n......\n......\ns..TH.\n"
+		);
+
+		let trail = render_trail(simulate(input, &config).last().unwrap());
+		assert_eq!(trail, "..##.\n...##\n.####\n....#\ns###.\n");
+
+		let input =
+			concat!("R 5\n", "U 8\n", "L 8\n", "D 3\n", "R 17\n", "D 10\n", "L 25\n", "U 20\n",);
+		let config = RopeConfig {
+			knots: 10,
+			..RopeConfig::default()
+		};
+		let frames = render_frames(&record(input, &config));
+		let last = frames.last().unwrap();
+		let mut expected = String::from("== U 20 ==\n\n");
+		for label in "H123456789".chars() {
+			expected += &format!("{}{}\n", label, ".".repeat(25));
+		}
+		expected += &format!("{}\n", ".".repeat(26)).repeat(5);
+		expected += "...........s..............\n";
+		expected += &format!("{}\n", ".".repeat(26)).repeat(5);
+		assert_eq!(*last, expected);
+
+		let dir = std::env::temp_dir().join(format!("day09-{}", std::process::id()));
+		let written = write_frames(&record("R 2\n", &config), &dir);
+		let files = fs::read_dir(&dir).map(|d| d.count());
+		let first = fs::read_to_string(dir.join("00000.txt"));
+		fs::remove_dir_all(&dir).unwrap();
+		assert!(written.is_ok());
+		assert_eq!(files.unwrap(), 3);
+		assert_eq!(first.unwrap(), "== Initial State ==\n\nH..\n");
 	}
 
 	#[test]
@@ -1,8 +1,11 @@
+use std::env;
+use std::fmt;
 use std::fs;
-use std::io::{stdout, Write};
 use std::path::Path;
-use std::thread::sleep;
-use std::time::Duration;
+
+mod ocr;
+
+use ocr::OcrError;
 
 pub fn run() {
 	let module_name = module_path!().split("::").last().unwrap();
@@ -13,125 +16,464 @@ pub fn run() {
 	let part01_result = part01(&input);
 	println!("part01 {:?}", part01_result);
 
-	let part02_result = part02(&input);
-	println!("part02{:?}", part02_result);
-
-	let short_pause = || sleep(Duration::from_millis(50));
-	let long_pause = || sleep(Duration::from_millis(600));
-	let flush = || stdout().flush().unwrap();
-	for (i, c) in part02_result.chars().enumerate() {
-		if i % 40 == 0 {
-			long_pause();
-			println!();
-			flush();
+	match part02(&input) {
+		Ok(letters) => println!("part02 {:?}", letters),
+		Err(e) => print!("part02 {}", e),
+	}
+	draw_crt(&input)
+		.as_bytes()
+		.chunks(40)
+		.for_each(|row| println!("{}", String::from_utf8_lossy(row)));
+
+	// lines of a file drive a debugger on request, e.g.
+	// `echo "break cycle 20" | DEBUG_FILE=/dev/stdin cargo run day10`
+	if let Some(file) = env::var_os("DEBUG_FILE") {
+		match fs::read_to_string(&file) {
+			Ok(commands) => {
+				let mut debugger = Debugger::new(parse_program(&input));
+				for command in commands.lines() {
+					match debugger.command(command) {
+						Ok(output) => print!("{}", output),
+						Err(e) => println!("{}", e),
+					}
+				}
+			}
+			Err(e) => println!("Could not read {}: {}", Path::new(&file).display(), e),
 		}
-		short_pause();
-		print!("{}", c);
-		flush();
 	}
-	long_pause();
-	println!();
-	long_pause();
-	println!();
-	long_pause();
-	println!("Done");
-	long_pause();
-	println!();
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Instruction {
 	Noop,
 	Addx(isize),
 }
 
+impl Instruction {
+	fn parse(line: &str) -> Self {
+		let mut parts = line.split_whitespace();
+		let name = parts.next().unwrap_or_default();
+		let mut arg = || {
+			parts
+				.next()
+				.and_then(|a| a.parse::<isize>().ok())
+				.unwrap_or_else(|| panic!("Missing argument in {}", line))
+		};
+
+		match name {
+			"noop" => Instruction::Noop,
+			"addx" => Instruction::Addx(arg()),
+			_ => panic!("Unknown instruction {}", line),
+		}
+	}
+
+	fn cycles(&self) -> usize {
+		match self {
+			Instruction::Noop => 1,
+			Instruction::Addx(_) => 2,
+		}
+	}
+
+	// applied once the instruction's last cycle is over
+	fn execute(&self, registers: &mut Registers) {
+		match self {
+			Instruction::Noop => {}
+			Instruction::Addx(v) => registers.x += v,
+		}
+	}
+}
+
+fn parse_program(input: &str) -> Vec<Instruction> {
+	input.lines().map(Instruction::parse).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Registers {
+	x: isize,
+}
+
+impl Default for Registers {
+	fn default() -> Self {
+		Registers { x: 1 }
+	}
+}
+
+// what the CPU looks like during a cycle, before the instruction finishing in
+// it has changed any register
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tick {
+	cycle: usize,
+	instruction: Instruction,
+	registers: Registers,
+}
+
+trait Observer {
+	fn on_cycle(&mut self, tick: &Tick);
+}
+
+impl<F: FnMut(&Tick)> Observer for F {
+	fn on_cycle(&mut self, tick: &Tick) {
+		self(tick)
+	}
+}
+
+struct Cpu {
+	program: Vec<Instruction>,
+	registers: Registers,
+	// cycles run so far
+	cycle: usize,
+	pc: usize,
+	// cycles already spent on the instruction at `pc`
+	busy: usize,
+}
+
+impl Cpu {
+	fn new(program: Vec<Instruction>) -> Self {
+		Cpu {
+			program,
+			registers: Registers::default(),
+			cycle: 0,
+			pc: 0,
+			busy: 0,
+		}
+	}
+
+	fn halted(&self) -> bool {
+		self.pc >= self.program.len()
+	}
+
+	// runs exactly one cycle
+	fn step(&mut self) -> Option<Tick> {
+		if self.halted() {
+			return None;
+		}
+
+		let instruction = self.program[self.pc];
+		self.cycle += 1;
+		let tick = Tick {
+			cycle: self.cycle,
+			instruction,
+			registers: self.registers,
+		};
+
+		self.busy += 1;
+		if self.busy == instruction.cycles() {
+			instruction.execute(&mut self.registers);
+			self.pc += 1;
+			self.busy = 0;
+		}
+
+		Some(tick)
+	}
+
+	fn run(&mut self, observers: &mut [&mut dyn Observer]) {
+		while let Some(tick) = self.step() {
+			observers.iter_mut().for_each(|o| o.on_cycle(&tick));
+		}
+	}
+}
+
+// sum of cycle times x for the cycles given
+struct SignalStrength {
+	cycles: Vec<usize>,
+	sum: isize,
+}
+
+impl Observer for SignalStrength {
+	fn on_cycle(&mut self, tick: &Tick) {
+		if self.cycles.contains(&tick.cycle) {
+			self.sum += tick.cycle as isize * tick.registers.x;
+		}
+	}
+}
+
+// one pixel drawn per cycle, lit when the sprite at x covers it
+struct Crt {
+	width: usize,
+	pixels: String,
+}
+
+impl Observer for Crt {
+	fn on_cycle(&mut self, tick: &Tick) {
+		let draw_pos = ((tick.cycle - 1) % self.width) as isize;
+		if get_sprite_pos(&tick.registers.x).contains(&draw_pos) {
+			self.pixels.push('#');
+		} else {
+			self.pixels.push('.');
+		}
+	}
+}
+
 fn part01(input: &str) -> isize {
-	let mut instructions = vec![];
+	let mut signal = SignalStrength {
+		cycles: (20..=220).step_by(40).collect(),
+		sum: 0,
+	};
+	Cpu::new(parse_program(input)).run(&mut [&mut signal]);
+
+	signal.sum
+}
+
+fn part02(input: &str) -> Result<String, OcrError> {
+	ocr::read(&draw_crt(input), 40)
+}
+
+fn draw_crt(input: &str) -> String {
+	let mut crt = Crt {
+		width: 40,
+		pixels: String::new(),
+	};
+	Cpu::new(parse_program(input)).run(&mut [&mut crt]);
+
+	crt.pixels
+}
+
+fn get_sprite_pos(x: &isize) -> [isize; 3] {
+	[x - 1, x + 0, x + 1]
+}
 
-	input.lines().for_each(|l| match l {
-		l if l.starts_with("noop") => {
-			instructions.push(Instruction::Noop);
+impl fmt::Display for Instruction {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Instruction::Noop => write!(f, "noop"),
+			Instruction::Addx(v) => write!(f, "addx {}", v),
 		}
-		l if l.starts_with("addx") => {
-			let (_i, s) = l.split_once(" ").unwrap();
-			let size = s.parse::<isize>().unwrap();
-			instructions.push(Instruction::Noop);
-			instructions.push(Instruction::Addx(size));
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Breakpoint {
+	Cycle(usize),
+	// x changing to this value, or already holding it in the first cycle
+	Register(isize),
+}
+
+impl Breakpoint {
+	fn hit(&self, tick: &Tick, previous: Option<&Tick>) -> bool {
+		match self {
+			Breakpoint::Cycle(c) => tick.cycle == *c,
+			Breakpoint::Register(x) => {
+				tick.registers.x == *x && previous.map_or(true, |p| p.registers.x != *x)
+			}
 		}
-		_ => panic!("Unknown instruction {}", l),
-	});
-
-	let mut x = 1_isize;
-	let to_check = (20..=220).step_by(40).collect::<Vec<_>>();
-	let mut checked = vec![];
-
-	instructions.iter().enumerate().for_each(|(c, i)| {
-		let cycle = isize::try_from(c).unwrap() + 1;
-		match i {
-			Instruction::Noop => {
-				if to_check.contains(&cycle) {
-					checked.push(cycle * x);
-				}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Factor {
+	X,
+	Cycle,
+	Number(isize),
+}
+
+// sums and differences of products of `x`, `cycle` and whole numbers, each of which
+// may be negated, e.g. `cycle*x` or `-x*-2`
+#[derive(Debug, PartialEq)]
+struct Watch {
+	source: String,
+	terms: Vec<(isize, Vec<Factor>)>,
+}
+
+impl Watch {
+	fn parse(source: &str) -> Result<Self, String> {
+		let expr = source.replace(' ', "");
+		let mut terms = vec![];
+		let mut sign = 1;
+		let mut start = 0;
+
+		for (i, c) in expr.char_indices().chain([(expr.len(), '+')]) {
+			if c != '+' && c != '-' {
+				continue;
 			}
-			Instruction::Addx(v) => {
-				if to_check.contains(&cycle) {
-					checked.push(cycle * x);
-				}
-				x += v;
+			// a minus starting a term or a factor negates it rather than subtracting
+			if c == '-' && (i == start || expr[..i].ends_with('*')) {
+				continue;
+			}
+
+			let mut factors = vec![];
+			for f in expr[start..i].split('*') {
+				let f = match f.strip_prefix('-') {
+					Some(negated) => {
+						sign = -sign;
+						negated
+					}
+					None => f,
+				};
+				factors.push(match f {
+					"x" => Factor::X,
+					"cycle" => Factor::Cycle,
+					_ => f
+						.parse()
+						.map(Factor::Number)
+						.map_err(|_| format!("Unknown value {:?} in {}", f, source))?,
+				});
 			}
+			terms.push((sign, factors));
+
+			sign = if c == '-' { -1 } else { 1 };
+			start = i + 1;
 		}
-	});
 
-	let signal_sum = checked.into_iter().sum();
+		Ok(Watch {
+			source: String::from(source),
+			terms,
+		})
+	}
 
-	signal_sum
+	fn eval(&self, tick: &Tick) -> isize {
+		self.terms
+			.iter()
+			.map(|(sign, factors)| {
+				let product = factors
+					.iter()
+					.map(|f| match f {
+						Factor::X => tick.registers.x,
+						Factor::Cycle => tick.cycle as isize,
+						Factor::Number(n) => *n,
+					})
+					.product::<isize>();
+				sign * product
+			})
+			.sum()
+	}
+}
+
+#[derive(Debug, PartialEq)]
+struct TraceEntry {
+	cycle: usize,
+	instruction: Instruction,
+	x_before: isize,
+	x_after: isize,
+}
+
+impl fmt::Display for TraceEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}\t{}\t{}\t{}",
+			self.cycle, self.instruction, self.x_before, self.x_after
+		)
+	}
 }
 
-fn part02(input: &str) -> String {
-	let mut instructions = vec![];
+struct Debugger {
+	cpu: Cpu,
+	breakpoints: Vec<Breakpoint>,
+	watches: Vec<Watch>,
+	// every cycle run so far
+	trace: Vec<TraceEntry>,
+	// the cycle the debugger is stopped at
+	last: Option<Tick>,
+}
 
-	input.lines().for_each(|l| match l {
-		l if l.starts_with("noop") => {
-			instructions.push(Instruction::Noop);
+impl Debugger {
+	fn new(program: Vec<Instruction>) -> Self {
+		Debugger {
+			cpu: Cpu::new(program),
+			breakpoints: vec![],
+			watches: vec![],
+			trace: vec![],
+			last: None,
 		}
-		l if l.starts_with("addx") => {
-			let (_i, s) = l.split_once(" ").unwrap();
-			let size = s.parse::<isize>().unwrap();
-			instructions.push(Instruction::Noop);
-			instructions.push(Instruction::Addx(size));
+	}
+
+	// runs a single cycle
+	fn step(&mut self) -> Option<Tick> {
+		let tick = self.cpu.step()?;
+		self.trace.push(TraceEntry {
+			cycle: tick.cycle,
+			instruction: tick.instruction,
+			x_before: tick.registers.x,
+			x_after: self.cpu.registers.x,
+		});
+		self.last = Some(tick);
+		Some(tick)
+	}
+
+	// runs until a cycle hits a breakpoint, or the program ends
+	fn cont(&mut self) -> Option<Tick> {
+		let mut previous = self.last;
+		while let Some(tick) = self.step() {
+			if self
+				.breakpoints
+				.iter()
+				.any(|b| b.hit(&tick, previous.as_ref()))
+			{
+				return Some(tick);
+			}
+			previous = Some(tick);
 		}
-		_ => panic!("Unknown instruction {}", l),
-	});
-
-	let mut x = 1_isize;
-	let mut display = vec![];
-
-	instructions.iter().enumerate().for_each(|(c, i)| {
-		let draw_pos = isize::try_from(c % 40).unwrap();
-		let sprite_pos = get_sprite_pos(&x);
-		match i {
-			Instruction::Noop => {
-				if sprite_pos.contains(&draw_pos) {
-					display.push('#');
-				} else {
-					display.push('.');
-				}
+		None
+	}
+
+	fn watched(&self, tick: &Tick) -> Vec<(&str, isize)> {
+		self.watches
+			.iter()
+			.map(|w| (w.source.as_str(), w.eval(tick)))
+			.collect()
+	}
+
+	fn describe(&self, tick: &Tick) -> String {
+		let watched = self
+			.watched(tick)
+			.iter()
+			.map(|(w, v)| format!(" {}={}", w, v))
+			.collect::<String>();
+		format!("cycle {}: {}{}\n", tick.cycle, tick.instruction, watched)
+	}
+
+	fn dump_trace(&self) -> String {
+		self.trace.iter().map(|t| format!("{}\n", t)).collect()
+	}
+
+	// break cycle N | break x N | watch EXPR | step [N] | continue | trace
+	fn command(&mut self, command: &str) -> Result<String, String> {
+		let count = |s: &str| {
+			s.parse::<usize>()
+				.map_err(|_| format!("Not a count: {}", s))
+		};
+		let value = |s: &str| {
+			s.parse::<isize>()
+				.map_err(|_| format!("Not a number: {}", s))
+		};
+		let args = command.split_whitespace().collect::<Vec<_>>();
+
+		match args[..] {
+			["break", "cycle", n] => {
+				self.breakpoints.push(Breakpoint::Cycle(count(n)?));
+				Ok(String::new())
+			}
+			["break", "x", n] => {
+				self.breakpoints.push(Breakpoint::Register(value(n)?));
+				Ok(String::new())
+			}
+			["watch", ..] if args.len() > 1 => {
+				let watch = Watch::parse(&args[1..].join(" "))?;
+				let now = self
+					.last
+					.map(|t| format!("{}={}\n", watch.source, watch.eval(&t)));
+				self.watches.push(watch);
+				Ok(now.unwrap_or_default())
 			}
-			Instruction::Addx(v) => {
-				if sprite_pos.contains(&draw_pos) {
-					display.push('#');
-				} else {
-					display.push('.');
+			["step"] | ["step", _] => {
+				let steps = args.get(1).map_or(Ok(1), |n| count(n))?;
+				let mut output = String::new();
+				for _ in 0..steps {
+					match self.step() {
+						Some(tick) => output += &self.describe(&tick),
+						None => return Ok(output + "halted\n"),
+					}
 				}
-				x += v;
+				Ok(output)
 			}
+			["continue"] => match self.cont() {
+				Some(tick) => Ok(self.describe(&tick)),
+				None => Ok(String::from("halted\n")),
+			},
+			["trace"] => Ok(self.dump_trace()),
+			_ => Err(format!("Unknown command: {}", command)),
 		}
-	});
-
-	display.iter().collect::<String>()
-}
-
-fn get_sprite_pos(x: &isize) -> [isize; 3] {
-	[x - 1, x + 0, x + 1]
+	}
 }
 
 #[cfg(test)]
@@ -287,6 +629,89 @@ mod tests {
 		"noop\n"
 	);
 
+	#[test]
+	fn test_cpu() {
+		let program = parse_program("noop\naddx 3\naddx -5\n");
+		assert_eq!(program[2], Instruction::Addx(-5));
+		assert_eq!(program.iter().map(|i| i.cycles()).sum::<usize>(), 5);
+
+		let mut cpu = Cpu::new(program);
+		let mut xs = vec![];
+		let mut cycles = vec![];
+		cpu.run(
+			&mut [&mut |t: &Tick| xs.push(t.registers.x), &mut |t: &Tick| {
+				cycles.push(t.cycle)
+			}],
+		);
+		assert_eq!(xs, vec![1, 1, 1, 4, 4]);
+		assert_eq!(cycles, vec![1, 2, 3, 4, 5]);
+		assert!(cpu.halted());
+		assert_eq!(cpu.registers.x, -1);
+		assert_eq!(cpu.step(), None);
+
+		let mut cpu = Cpu::new(parse_program(INPUT));
+		let mut signal = SignalStrength {
+			cycles: vec![20, 60],
+			sum: 0,
+		};
+		let mut crt = Crt {
+			width: 40,
+			pixels: String::new(),
+		};
+		cpu.run(&mut [&mut signal, &mut crt]);
+		assert_eq!(signal.sum, 420 + 1140);
+		assert_eq!(crt.pixels.len(), 240);
+	}
+
+	#[test]
+	fn test_watch() {
+		let tick = Tick {
+			cycle: 20,
+			instruction: Instruction::Noop,
+			registers: Registers { x: 21 },
+		};
+		assert_eq!(Watch::parse("x").unwrap().eval(&tick), 21);
+		assert_eq!(Watch::parse("cycle*x").unwrap().eval(&tick), 420);
+		assert_eq!(Watch::parse("cycle - x*2 + 3").unwrap().eval(&tick), -19);
+		assert_eq!(Watch::parse("-x").unwrap().eval(&tick), -21);
+		assert_eq!(Watch::parse("cycle*-2 - -x").unwrap().eval(&tick), -19);
+		assert_eq!(Watch::parse("-x*-cycle").unwrap().eval(&tick), 420);
+		assert!(Watch::parse("cycle*y").is_err());
+		assert!(Watch::parse("x*-").is_err());
+		assert_eq!(Watch::parse("x--2").unwrap().eval(&tick), 23);
+	}
+
+	#[test]
+	fn test_debugger() {
+		let mut debugger = Debugger::new(parse_program(INPUT));
+		let mut run = |command: &str| debugger.command(command).unwrap();
+
+		assert_eq!(run("watch cycle*x"), "");
+		assert_eq!(run("break cycle 20"), "");
+		assert_eq!(run("break cycle 60"), "");
+		assert_eq!(run("continue"), "cycle 20: addx -1 cycle*x=420\n");
+		assert_eq!(run("watch x"), "x=21\n");
+		assert_eq!(
+			run("step 2"),
+			"cycle 21: addx -1 cycle*x=441 x=21\ncycle 22: addx 5 cycle*x=440 x=20\n"
+		);
+		assert_eq!(run("continue"), "cycle 60: addx -3 cycle*x=1140 x=19\n");
+		assert_eq!(run("break x -1"), "");
+		assert_eq!(run("continue"), "cycle 210: addx 22 cycle*x=-210 x=-1\n");
+
+		let trace = run("trace");
+		assert_eq!(trace.lines().count(), 210);
+		assert!(trace.starts_with("1\taddx 15\t1\t1\n2\taddx 15\t1\t16\n"));
+
+		// x stays -1 in cycle 211 and never changes back to it
+		assert_eq!(run("continue"), "halted\n");
+
+		assert!(run("step 1000").ends_with("halted\n"));
+		assert_eq!(run("continue"), "halted\n");
+		assert!(debugger.command("break pc 3").is_err());
+		assert_eq!(debugger.trace.len(), 240);
+	}
+
 	#[test]
 	fn test_part01() {
 		let result = part01(INPUT);
@@ -303,7 +728,16 @@ mod tests {
 			"######......######......######......####",
 			"#######.......#######.......#######.....",
 		);
-		let result = part02(INPUT);
+		let result = draw_crt(INPUT);
 		assert_eq!(&result, expected);
+
+		// the example draws a test pattern rather than letters
+		match part02(INPUT) {
+			Err(OcrError::UnknownGlyph { index, glyph }) => {
+				assert_eq!(index, 0);
+				assert_eq!(glyph, "##..###.################");
+			}
+			other => panic!("Expected an unknown glyph, got {:?}", other),
+		}
 	}
 }
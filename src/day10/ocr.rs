@@ -0,0 +1,138 @@
+use std::fmt;
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+// blank columns between two letters
+const SPACING: usize = 1;
+
+// every known letter, its rows joined top to bottom
+const GLYPHS: [(char, &str); 17] = [
+	('A', ".##.#..##..######..##..#"),
+	('B', "###.#..####.#..##..####."),
+	('C', ".##.#..##...#...#..#.##."),
+	('E', "#####...###.#...#...####"),
+	('F', "#####...###.#...#...#..."),
+	('G', ".##.#..##...#.###..#.###"),
+	('H', "#..##..######..##..##..#"),
+	('I', ".###..#...#...#...#..###"),
+	('J', "..##...#...#...##..#.##."),
+	('K', "#..##.#.##..#.#.#.#.#..#"),
+	('L', "#...#...#...#...#...####"),
+	('O', ".##.#..##..##..##..#.##."),
+	('P', "###.#..##..####.#...#..."),
+	('R', "###.#..##..####.#.#.#..#"),
+	('S', ".####...#....##....####."),
+	('U', "#..##..##..##..##..#.##."),
+	('Z', "####...#..#..#..#...####"),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+	// the pixels don't make up whole rows of `GLYPH_HEIGHT`
+	BadSize { pixels: usize, width: usize },
+	UnknownGlyph { index: usize, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			OcrError::BadSize { pixels, width } => {
+				write!(
+					f,
+					"{} pixels are not {} rows of {}",
+					pixels, GLYPH_HEIGHT, width
+				)
+			}
+			OcrError::UnknownGlyph { index, glyph } => {
+				writeln!(f, "unknown glyph at letter {}:", index)?;
+				for row in glyph.as_bytes().chunks(GLYPH_WIDTH) {
+					writeln!(f, "{}", String::from_utf8_lossy(row))?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+// reads the letters off a screen of `#` and `.`, given row by row without line breaks
+pub fn read(pixels: &str, width: usize) -> Result<String, OcrError> {
+	if width == 0 || pixels.len() != width * GLYPH_HEIGHT {
+		return Err(OcrError::BadSize {
+			pixels: pixels.len(),
+			width,
+		});
+	}
+
+	let rows = pixels.as_bytes().chunks(width).collect::<Vec<_>>();
+	let letters = (width + SPACING) / (GLYPH_WIDTH + SPACING);
+
+	(0..letters)
+		.map(|index| {
+			let x = index * (GLYPH_WIDTH + SPACING);
+			let glyph = rows
+				.iter()
+				.map(|row| String::from_utf8_lossy(&row[x..(x + GLYPH_WIDTH)]))
+				.collect::<String>();
+
+			GLYPHS
+				.iter()
+				.find(|(_, g)| *g == glyph)
+				.map(|(letter, _)| *letter)
+				.ok_or(OcrError::UnknownGlyph { index, glyph })
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn screen(rows: [&str; GLYPH_HEIGHT]) -> String {
+		rows.concat()
+	}
+
+	#[test]
+	fn test_glyphs() {
+		for (letter, glyph) in GLYPHS {
+			assert_eq!(glyph.len(), GLYPH_WIDTH * GLYPH_HEIGHT, "{}", letter);
+			assert_eq!(read(glyph, GLYPH_WIDTH), Ok(String::from(letter)));
+		}
+	}
+
+	#[test]
+	fn test_read() {
+		let pixels = screen([
+			"#..#.####.#....#.....##..",
+			"#..#.#....#....#....#..#.",
+			"####.###..#....#....#..#.",
+			"#..#.#....#....#....#..#.",
+			"#..#.#....#....#....#..#.",
+			"#..#.####.####.####..##..",
+		]);
+		assert_eq!(read(&pixels, 25), Ok(String::from("HELLO")));
+
+		let pixels = screen([
+			"#..#.#...",
+			"#..#.#...",
+			"####.#...",
+			"#..#.#...",
+			"#..#.#...",
+			"#..#.###.",
+		]);
+		assert_eq!(
+			read(&pixels, 9),
+			Err(OcrError::UnknownGlyph {
+				index: 1,
+				glyph: String::from("#...#...#...#...#...###."),
+			})
+		);
+
+		assert_eq!(
+			read("####", 4),
+			Err(OcrError::BadSize {
+				pixels: 4,
+				width: 4
+			})
+		);
+	}
+}
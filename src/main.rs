@@ -24,6 +24,8 @@ mod day12;
 mod day13;
 mod day14;
 mod day15;
+#[cfg(test)]
+mod test_input;
 
 fn main() {
 	let args: Vec<String> = env::args().collect();
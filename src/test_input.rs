@@ -0,0 +1,7 @@
+// the same pseudo-random numbers for a seed on every run, to generate big inputs with
+pub fn numbers(mut seed: u64) -> impl FnMut() -> usize {
+	move || {
+		seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+		(seed >> 33) as usize
+	}
+}